@@ -0,0 +1,351 @@
+use crate::stats::t_critical_95;
+use crate::{QueryStatus, Result, Results, Statistics};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct CompareOpt {
+    /// Results files to compare. The first file is the baseline.
+    #[structopt(parse(from_os_str), min_values = 2, required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// A query regresses when it is slower than the baseline by more than this percentage
+    #[structopt(long, default_value = "5")]
+    pub threshold: f64,
+}
+
+/// Comparison of one query between the baseline and another results file
+#[derive(Debug)]
+struct QueryComparison {
+    query: String,
+    baseline: Statistics,
+    other: Statistics,
+    /// Change in mean time as a percentage of the baseline. Positive values are slower.
+    change: f64,
+    /// Whether the difference in means is significant at the 95% level. `None` when there are
+    /// not enough iterations to tell.
+    significant: Option<bool>,
+}
+
+impl QueryComparison {
+    fn new(query: String, baseline_times: &[u128], other_times: &[u128]) -> Self {
+        let baseline = Statistics::from_times(baseline_times);
+        let other = Statistics::from_times(other_times);
+        let change = if baseline.mean > 0.0 {
+            (other.mean - baseline.mean) / baseline.mean * 100.0
+        } else {
+            0.0
+        };
+        let significant = welch_t_test(&baseline, &other);
+        Self {
+            query,
+            baseline,
+            other,
+            change,
+            significant,
+        }
+    }
+
+    fn is_regression(&self, threshold: f64) -> bool {
+        self.change > threshold && self.significant != Some(false)
+    }
+}
+
+/// Welch's t-test for a difference in means at the 95% level
+fn welch_t_test(a: &Statistics, b: &Statistics) -> Option<bool> {
+    if a.iterations < 2 || b.iterations < 2 {
+        return None;
+    }
+    let va = a.stddev.powi(2) / a.iterations as f64;
+    let vb = b.stddev.powi(2) / b.iterations as f64;
+    if va + vb == 0.0 {
+        return Some(a.mean != b.mean);
+    }
+    let t = (a.mean - b.mean).abs() / (va + vb).sqrt();
+    let df = (va + vb).powi(2)
+        / (va.powi(2) / (a.iterations - 1) as f64 + vb.powi(2) / (b.iterations - 1) as f64);
+    Some(t > t_critical_95((df.floor() as usize).max(1)))
+}
+
+/// Compare results files against the first (baseline) file, printing per-query changes and
+/// the geometric mean change. Returns the number of regressions past the threshold, including
+/// queries that are missing from a file, and queries that failed or returned a wrong answer
+/// but did not in the baseline.
+pub fn compare(opt: &CompareOpt) -> Result<usize> {
+    let baseline = read_results(&opt.files[0])?;
+    let mut regressions = 0;
+
+    for file in &opt.files[1..] {
+        let other = read_results(file)?;
+        println!(
+            "Comparing {} ({}) with baseline {} ({})",
+            file.display(),
+            other.engine,
+            opt.files[0].display(),
+            baseline.engine
+        );
+        println!(
+            "{:<8}{:>14}{:>14}{:>10}{:>10}  Significant",
+            "Query", "Baseline (ms)", "New (ms)", "Change", "Speedup"
+        );
+
+        let mut log_ratio_sum = 0.0;
+        let mut count = 0;
+        for base in &baseline.query_results {
+            let other_result = match other.query_results.iter().find(|r| r.query == base.query) {
+                Some(r) => r,
+                None => {
+                    println!("{:<8}missing  REGRESSION", base.query);
+                    regressions += 1;
+                    continue;
+                }
+            };
+            // a query that now fails or returns a wrong answer regresses, however fast it is
+            if other_result.is_failure() {
+                let regression = !base.is_failure();
+                if regression {
+                    regressions += 1;
+                }
+                let outcome = match &other_result.validation {
+                    Some(v) if !other_result.status.is_failure() => {
+                        format!("validation {:?}", v.status)
+                    }
+                    _ => other_result.status.to_string(),
+                };
+                println!(
+                    "{:<8}{}{}",
+                    base.query,
+                    outcome,
+                    if regression { "  REGRESSION" } else { "" }
+                );
+                continue;
+            }
+            if other_result.status != QueryStatus::Ok {
+                println!("{:<8}{}", base.query, other_result.status);
+                continue;
+            }
+            if base.times.is_empty() || other_result.times.is_empty() {
+                continue;
+            }
+            let comparison =
+                QueryComparison::new(base.query.clone(), &base.times, &other_result.times);
+            let regression = comparison.is_regression(opt.threshold);
+            if regression {
+                regressions += 1;
+            }
+            let speedup = if comparison.other.mean > 0.0 {
+                comparison.baseline.mean / comparison.other.mean
+            } else {
+                f64::INFINITY
+            };
+            println!(
+                "{:<8}{:>14.1}{:>14.1}{:>9.1}%{:>9.2}x  {}{}",
+                comparison.query,
+                comparison.baseline.mean,
+                comparison.other.mean,
+                comparison.change,
+                speedup,
+                match comparison.significant {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "n/a",
+                },
+                if regression { "  REGRESSION" } else { "" }
+            );
+            if comparison.baseline.mean > 0.0 && comparison.other.mean > 0.0 {
+                log_ratio_sum += (comparison.other.mean / comparison.baseline.mean).ln();
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            let geomean = (log_ratio_sum / count as f64).exp();
+            println!(
+                "Geometric mean change: {:.1}% ({:.2}x speedup)",
+                (geomean - 1.0) * 100.0,
+                1.0 / geomean
+            );
+        }
+        println!();
+    }
+
+    if regressions > 0 {
+        println!(
+            "{} queries regressed by more than {}%",
+            regressions, opt.threshold
+        );
+    }
+    Ok(regressions)
+}
+
+/// Read a results file, which must have results for at least one query
+fn read_results(path: &Path) -> Result<Results> {
+    let results = Results::read(path)?;
+    if results.query_results.is_empty() {
+        return Err(format!("No query results in {}", path.display()).into());
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Validation;
+    use crate::QueryResult;
+    use std::fs;
+
+    fn stats(times: &[u128]) -> Statistics {
+        Statistics::from_times(times)
+    }
+
+    /// Write results files to a temporary directory, the first being the baseline
+    fn write_files(name: &str, files: &[Vec<QueryResult>]) -> Vec<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("sqlbench-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        files
+            .iter()
+            .enumerate()
+            .map(|(i, query_results)| {
+                let mut results = Results::new();
+                results.query_results = query_results.clone();
+                let path = dir.join(format!("results-{}.json", i));
+                fs::write(&path, serde_json::to_string(&results).unwrap()).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn regressions(files: Vec<PathBuf>, threshold: f64) -> usize {
+        let regressions = compare(&CompareOpt {
+            files: files.clone(),
+            threshold,
+        })
+        .unwrap();
+        fs::remove_dir_all(files[0].parent().unwrap()).unwrap();
+        regressions
+    }
+
+    fn ok(query: &str, times: &[u128]) -> QueryResult {
+        QueryResult::new(query.to_string(), vec![], times.to_vec())
+    }
+
+    fn failed(query: &str, status: QueryStatus) -> QueryResult {
+        QueryResult::incomplete(query.to_string(), status, None)
+    }
+
+    const BASELINE: [u128; 5] = [100, 102, 98, 101, 99];
+
+    #[test]
+    fn welch_t_test_with_equal_variances() {
+        // t = 5.0, df = 8, p = 0.0011
+        let significant =
+            welch_t_test(&stats(&[10, 11, 12, 13, 14]), &stats(&[15, 16, 17, 18, 19]));
+        assert_eq!(significant, Some(true));
+        // t = 3.0, df = 8, p = 0.017
+        let significant = welch_t_test(&stats(&BASELINE), &stats(&[103, 105, 101, 104, 102]));
+        assert_eq!(significant, Some(true));
+        // t = 2.0, df = 8, p = 0.081
+        let significant = welch_t_test(&stats(&BASELINE), &stats(&[102, 104, 100, 103, 101]));
+        assert_eq!(significant, Some(false));
+        // t = 0.61, df = 4, p = 0.57
+        let significant = welch_t_test(&stats(&[10, 12, 14]), &stats(&[11, 13, 15]));
+        assert_eq!(significant, Some(false));
+    }
+
+    #[test]
+    fn welch_t_test_with_unequal_variances() {
+        let noisy = [100, 110, 90, 105, 95];
+        // t = 3.33, df = 4.32, p = 0.026
+        let significant = welch_t_test(&stats(&noisy), &stats(&[112, 113, 111, 114, 110]));
+        assert_eq!(significant, Some(true));
+        // t = 2.50, df = 4.32, p = 0.062
+        let significant = welch_t_test(&stats(&noisy), &stats(&[109, 110, 108, 111, 107]));
+        assert_eq!(significant, Some(false));
+    }
+
+    #[test]
+    fn welch_t_test_needs_two_iterations() {
+        assert_eq!(welch_t_test(&stats(&[100]), &stats(&BASELINE)), None);
+        assert_eq!(welch_t_test(&stats(&[5, 5]), &stats(&[5, 5])), Some(false));
+        assert_eq!(welch_t_test(&stats(&[5, 5]), &stats(&[6, 6])), Some(true));
+    }
+
+    #[test]
+    fn regression_threshold() {
+        let slower = [110, 112, 108, 111, 109];
+        let files = vec![vec![ok("q1", &BASELINE)], vec![ok("q1", &slower)]];
+        assert_eq!(regressions(write_files("threshold-5", &files), 5.0), 1);
+        assert_eq!(regressions(write_files("threshold-15", &files), 15.0), 0);
+    }
+
+    #[test]
+    fn insignificant_slowdown_is_not_a_regression() {
+        let files = vec![vec![ok("q1", &[10, 12, 14])], vec![ok("q1", &[11, 13, 15])]];
+        assert_eq!(regressions(write_files("insignificant", &files), 5.0), 0);
+    }
+
+    #[test]
+    fn speedup_is_not_a_regression() {
+        let files = vec![
+            vec![ok("q1", &BASELINE)],
+            vec![ok("q1", &[50, 51, 49, 50, 50])],
+        ];
+        assert_eq!(regressions(write_files("speedup", &files), 5.0), 0);
+    }
+
+    #[test]
+    fn missing_query_is_a_regression() {
+        let files = vec![
+            vec![ok("q1", &BASELINE), ok("q2", &BASELINE)],
+            vec![ok("q1", &BASELINE), ok("q3", &BASELINE)],
+        ];
+        assert_eq!(regressions(write_files("missing", &files), 5.0), 1);
+    }
+
+    #[test]
+    fn new_failures_are_regressions() {
+        let mut wrong_answer = ok("q3", &[50, 50, 50]);
+        wrong_answer.validation = Some(Validation::mismatch("row 1 differs".to_string()));
+        let files = vec![
+            vec![
+                ok("q1", &BASELINE),
+                ok("q2", &BASELINE),
+                ok("q3", &BASELINE),
+                failed("q4", QueryStatus::Failed),
+            ],
+            vec![
+                failed("q1", QueryStatus::Failed),
+                failed("q2", QueryStatus::TimedOut),
+                wrong_answer,
+                failed("q4", QueryStatus::Failed),
+            ],
+        ];
+        // q4 already failed in the baseline
+        assert_eq!(regressions(write_files("failures", &files), 5.0), 3);
+    }
+
+    #[test]
+    fn legacy_baseline() {
+        let files = write_files(
+            "legacy",
+            &[vec![], vec![ok("q1", &[110, 112, 108, 111, 109])]],
+        );
+        fs::write(
+            &files[0],
+            r#"{"query_times": [[1, [100, 102, 98, 101, 99]]]}"#,
+        )
+        .unwrap();
+        assert_eq!(regressions(files, 5.0), 1);
+    }
+
+    #[test]
+    fn file_without_query_results() {
+        let files = write_files("empty", &[vec![ok("q1", &BASELINE)], vec![]]);
+        let result = compare(&CompareOpt {
+            files: files.clone(),
+            threshold: 5.0,
+        });
+        fs::remove_dir_all(files[0].parent().unwrap()).unwrap();
+        assert!(result.is_err());
+    }
+}
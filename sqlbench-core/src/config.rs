@@ -0,0 +1,24 @@
+use crate::Result;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Read `key=value` pairs from a properties file. Lines starting with `#` are comments.
+pub fn read_config_file(config_path: &Path) -> Result<Vec<(String, String)>> {
+    let file = File::open(config_path)?;
+    let reader = BufReader::new(file);
+    let mut entries = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+        let parts = line.split('=').collect::<Vec<&str>>();
+        if parts.len() == 2 {
+            entries.push((parts[0].to_string(), parts[1].to_string()));
+        } else {
+            println!("Warning! Skipping config entry {}", line);
+        }
+    }
+    Ok(entries)
+}
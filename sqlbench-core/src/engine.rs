@@ -0,0 +1,141 @@
+use crate::{Error, ErrorCategory, Opt, QueryMetrics, Result, Table};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How query results are consumed during execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultMode {
+    /// Keep all result batches in memory
+    Collect,
+    /// Drop result batches as they arrive
+    Stream,
+    /// Count the rows and bytes of result batches as they arrive, then drop them
+    Count,
+}
+
+impl FromStr for ResultMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "collect" => Ok(ResultMode::Collect),
+            "stream" => Ok(ResultMode::Stream),
+            "count" => Ok(ResultMode::Count),
+            _ => Err(format!("Unsupported result mode: {}", s)),
+        }
+    }
+}
+
+/// Summary of the output of an executed statement
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputSummary {
+    /// Rows returned, unless results were streamed
+    pub rows: Option<usize>,
+    /// In-memory size of the returned batches in bytes, unless results were streamed
+    pub bytes: Option<usize>,
+    /// Time from the start of execution to the first batch, if any batch was returned
+    pub time_to_first_batch: Option<Duration>,
+}
+
+/// A query engine that can be benchmarked by the shared runner.
+#[async_trait]
+pub trait Engine: Sized + Send + Sync {
+    /// A logical plan
+    type Plan: Send + Sync;
+
+    /// A physical plan, ready to be executed
+    type PhysicalPlan: Send + Sync;
+
+    /// The output produced by executing a query
+    type Output: Send;
+
+    /// Create the engine from the command line options, applying any config file
+    async fn try_new(opt: &Opt) -> Result<Self>;
+
+    /// Version of the engine crate
+    fn engine_version(&self) -> String;
+
+    /// Version of DataFusion this engine was built against
+    fn datafusion_version(&self) -> String;
+
+    /// Versions and sources of the crates the engine was built with
+    fn build_info(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    /// Versions reported by the cluster the engine runs on
+    async fn cluster_versions(&self) -> Result<BTreeMap<String, String>> {
+        Ok(BTreeMap::new())
+    }
+
+    /// Effective configuration settings, recorded in the results file
+    fn config(&self) -> HashMap<String, String>;
+
+    /// Register a table with the engine
+    async fn register_table(&self, table: &Table) -> Result<()>;
+
+    /// Parse a single SQL statement into a logical plan
+    async fn plan(&self, sql: &str) -> Result<Self::Plan>;
+
+    /// Category of an error returned by the engine, if the engine can tell. Otherwise errors are
+    /// categorized by the phase of the query they occurred in.
+    fn error_category(&self, _error: &Error) -> Option<ErrorCategory> {
+        None
+    }
+
+    /// Optimize a logical plan. Engines that optimize as part of execution return the plan
+    /// unchanged.
+    fn optimize(&self, plan: Self::Plan) -> Result<Self::Plan> {
+        Ok(plan)
+    }
+
+    /// Create the physical plan for an optimized logical plan
+    async fn create_physical_plan(&self, plan: &Self::Plan) -> Result<Self::PhysicalPlan>;
+
+    /// Whether [`Engine::create_physical_plan`] creates the physical plan. Engines that create
+    /// it as part of execution, such as on a cluster scheduler, don't record a time for it.
+    fn creates_physical_plan(&self) -> bool {
+        true
+    }
+
+    /// Execute a physical plan and return its output. Result batches are only kept in the
+    /// output in [`ResultMode::Collect`] mode.
+    async fn execute(&self, plan: &Self::PhysicalPlan, mode: ResultMode) -> Result<Self::Output>;
+
+    /// Cancel any query still running after its execution was dropped, e.g. a job on a cluster
+    async fn cancel(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Row count, size and time to first batch of the query output
+    fn output_summary(&self, output: &Self::Output) -> OutputSummary;
+
+    /// Formatted optimized logical plan
+    fn explain(&self, plan: &Self::Plan) -> Result<String>;
+
+    /// Executed physical plan with per-operator metrics, if available from this engine
+    fn physical_plan(&self, _output: &Self::Output) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Metrics of the operators of the executed physical plan, if available from this engine
+    fn metrics(&self, _output: &Self::Output) -> Result<Option<QueryMetrics>> {
+        Ok(None)
+    }
+
+    /// Optimized logical plan in QPML format, if supported by this build
+    fn qpml(&self, _plan: &Self::Plan) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Whether the planned query returns rows in a defined order (i.e. has an ORDER BY)
+    fn is_ordered(&self, plan: &Self::Plan) -> Result<bool>;
+
+    /// Query output as rows of formatted values, with NULL as an empty string
+    fn output_rows(&self, output: &Self::Output) -> Result<Vec<Vec<String>>>;
+
+    /// Write query output to `{prefix}.csv`, and to `{prefix}.parquet` if `parquet` is true
+    async fn write_output(&self, output: Self::Output, prefix: &str, parquet: bool) -> Result<()>;
+}
@@ -0,0 +1,257 @@
+use crate::{natural_cmp, PhaseTimes, QueryResult, QueryStatus, Result, Results};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Name of the event log in the output directory
+pub const EVENT_LOG: &str = "events.jsonl";
+
+/// An event in the event log. Each event is written as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The tables were registered and the queries are about to run
+    RunStarted { results: Results },
+    /// An iteration of a query completed
+    Iteration {
+        query: String,
+        iteration: u32,
+        warmup: bool,
+        /// Duration of the iteration in milliseconds
+        time: u128,
+        phase_times: PhaseTimes,
+    },
+    /// A query completed, failed or was skipped
+    QueryFinished { result: QueryResult },
+}
+
+/// Externally tagged definition of [`Event`] to deserialize it with
+#[derive(Deserialize)]
+#[serde(remote = "Event", rename_all = "snake_case")]
+enum EventDef {
+    RunStarted {
+        results: Results,
+    },
+    Iteration {
+        query: String,
+        iteration: u32,
+        warmup: bool,
+        time: u128,
+        phase_times: PhaseTimes,
+    },
+    QueryFinished {
+        result: QueryResult,
+    },
+}
+
+impl<'de> Deserialize<'de> for Event {
+    /// Serde buffers the fields of internally tagged enums in a way that doesn't support the u128
+    /// times, so the `event` tag is moved out to deserialize the event as externally tagged
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut fields = serde_json::Map::deserialize(deserializer)?;
+        let tag = match fields.remove("event") {
+            Some(Value::String(tag)) => tag,
+            _ => return Err(D::Error::missing_field("event")),
+        };
+        let event = Value::Object(std::iter::once((tag, Value::Object(fields))).collect());
+        EventDef::deserialize(event).map_err(D::Error::custom)
+    }
+}
+
+/// Event log that is appended to and flushed after each event, so that the results collected
+/// so far survive a crash of the runner
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    /// Create an empty event log, replacing any existing log
+    pub fn create(path: &Path) -> Result<Self> {
+        File::create(path)?;
+        Self::open(path)
+    }
+
+    /// Open an event log to append to, creating it if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    pub fn append(&mut self, event: &Event) -> Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Build the results of a run from its event log. The header comes from the last
+    /// `run_started` event and each query from its last `query_finished` event.
+    pub fn read_results(path: &Path) -> Result<Results> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut results = None;
+        let mut query_results: Vec<QueryResult> = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Event>(&line) {
+                Ok(Event::RunStarted { results: r }) => results = Some(r),
+                Ok(Event::QueryFinished { result }) => {
+                    query_results.retain(|r| r.query != result.query);
+                    query_results.push(result);
+                }
+                Ok(Event::Iteration { .. }) => {}
+                // the last line is incomplete when the runner was killed while writing it
+                Err(e) => println!(
+                    "Warning! Skipping invalid event in {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        let mut results =
+            results.ok_or_else(|| format!("No run_started event in {}", path.display()))?;
+        query_results.sort_by(|a, b| natural_cmp(&a.query, &b.query));
+        results.query_results = query_results;
+        Ok(results)
+    }
+
+    /// Results of the queries that completed in a previous run, to be skipped when resuming it.
+    /// Queries that returned a wrong answer count as failed, as they do for the exit status.
+    pub fn completed_queries(path: &Path) -> Result<Vec<QueryResult>> {
+        let mut query_results = Self::read_results(path)?.query_results;
+        query_results.retain(|r| r.status == QueryStatus::Ok && !r.is_failure());
+        Ok(query_results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Validation;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Path of an event log in the temporary directory with the given lines
+    fn write_log(name: &str, lines: &[String]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("sqlbench-{}-{}.jsonl", std::process::id(), name));
+        fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    fn run_started() -> String {
+        serde_json::to_string(&Event::RunStarted {
+            results: Results::new(),
+        })
+        .unwrap()
+    }
+
+    fn query_finished(result: QueryResult) -> String {
+        serde_json::to_string(&Event::QueryFinished { result }).unwrap()
+    }
+
+    fn ok(query: &str) -> QueryResult {
+        QueryResult::new(query.to_string(), vec![], vec![10, 12])
+    }
+
+    fn queries(results: &[QueryResult]) -> Vec<&str> {
+        results.iter().map(|r| r.query.as_str()).collect()
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let events = vec![
+            Event::RunStarted {
+                results: Results::new(),
+            },
+            Event::Iteration {
+                query: "q1".to_string(),
+                iteration: 0,
+                warmup: false,
+                time: 12,
+                phase_times: PhaseTimes::default(),
+            },
+            Event::QueryFinished { result: ok("q1") },
+        ];
+        for event in events {
+            let line = serde_json::to_string(&event).unwrap();
+            assert_eq!(serde_json::from_str::<Event>(&line).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn truncated_last_line() {
+        let mut truncated = query_finished(ok("q2"));
+        truncated.truncate(truncated.len() / 2);
+        let path = write_log(
+            "truncated",
+            &[run_started(), query_finished(ok("q1")), truncated],
+        );
+        let results = EventLog::read_results(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(queries(&results.query_results), vec!["q1"]);
+    }
+
+    #[test]
+    fn last_query_finished_event_wins() {
+        let failed = QueryResult::incomplete("q1".to_string(), QueryStatus::Failed, None);
+        let path = write_log(
+            "duplicates",
+            &[
+                run_started(),
+                query_finished(ok("q10")),
+                query_finished(failed),
+                run_started(),
+                query_finished(ok("q1")),
+            ],
+        );
+        let results = EventLog::read_results(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(queries(&results.query_results), vec!["q1", "q10"]);
+        assert_eq!(results.query_results[0].status, QueryStatus::Ok);
+    }
+
+    #[test]
+    fn missing_run_started() {
+        let path = write_log("no-header", &[query_finished(ok("q1"))]);
+        let results = EventLog::read_results(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(results.is_err());
+    }
+
+    #[test]
+    fn completed_queries_exclude_failures_and_wrong_answers() {
+        let mut wrong_answer = ok("q3");
+        wrong_answer.validation = Some(Validation::mismatch("row 1 differs".to_string()));
+        let mut right_answer = ok("q4");
+        right_answer.validation = Some(Validation::pass());
+        let path = write_log(
+            "completed",
+            &[
+                run_started(),
+                query_finished(ok("q1")),
+                query_finished(QueryResult::incomplete(
+                    "q2".to_string(),
+                    QueryStatus::TimedOut,
+                    None,
+                )),
+                query_finished(wrong_answer),
+                query_finished(right_answer),
+                query_finished(QueryResult::incomplete(
+                    "q5".to_string(),
+                    QueryStatus::Skipped,
+                    None,
+                )),
+            ],
+        );
+        let completed = EventLog::completed_queries(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(queries(&completed), vec!["q1", "q4"]);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Tells the runner when it was interrupted with SIGINT (Ctrl-C) or SIGTERM. The first signal
+/// cancels the running query so that the results collected so far can be written, and a second
+/// signal exits immediately, killing the running child process of `--isolation` if any.
+#[derive(Clone)]
+pub struct Interrupt {
+    receiver: watch::Receiver<bool>,
+    child: Arc<Mutex<Option<u32>>>,
+}
+
+impl Interrupt {
+    /// Start listening for signals
+    pub fn listen() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        let child = Arc::new(Mutex::new(None));
+        let running_child = child.clone();
+        tokio::spawn(async move {
+            let mut signals = match Signals::new() {
+                Ok(signals) => signals,
+                Err(e) => {
+                    println!("Warning! Failed to listen for signals: {}", e);
+                    return;
+                }
+            };
+            signals.recv().await;
+            println!("Interrupted, cancelling the running query. Interrupt again to exit now.");
+            let _ = sender.send(true);
+            signals.recv().await;
+            eprintln!("Interrupted again, exiting without writing results");
+            if let Some(pid) = *running_child.lock().unwrap() {
+                if let Err(e) = kill_process(pid) {
+                    eprintln!("Failed to kill query process {}: {}", pid, e);
+                }
+            }
+            std::process::exit(130);
+        });
+        Self { receiver, child }
+    }
+
+    /// Set the process id of the running child process, which is killed when exiting on a
+    /// second signal
+    pub fn set_child(&self, pid: Option<u32>) {
+        *self.child.lock().unwrap() = pid;
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Wait until the runner is interrupted
+    pub async fn wait(&mut self) {
+        while !self.is_interrupted() {
+            if self.receiver.changed().await.is_err() {
+                // not listening for signals, so never interrupted
+                futures::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Interrupt a child process, as if it was interrupted with Ctrl-C
+#[cfg(unix)]
+pub fn interrupt_process(pid: u32) -> std::io::Result<()> {
+    // SAFETY: kill only sends a signal and has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Interrupt a child process. Without process groups, Ctrl-C already reaches all processes
+/// attached to the console.
+#[cfg(not(unix))]
+pub fn interrupt_process(_pid: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Kill a child process
+#[cfg(unix)]
+fn kill_process(pid: u32) -> std::io::Result<()> {
+    // SAFETY: kill only sends a signal and has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Kill a child process
+#[cfg(not(unix))]
+fn kill_process(pid: u32) -> std::io::Result<()> {
+    std::process::Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .status()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    async fn recv(&mut self) {
+        let interrupt = Box::pin(self.interrupt.recv());
+        let terminate = Box::pin(self.terminate.recv());
+        futures::future::select(interrupt, terminate).await;
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
@@ -0,0 +1,207 @@
+use crate::interrupt::{interrupt_process, Interrupt};
+use crate::stats::Statistics;
+use crate::{Crash, ErrorCategory, Opt, QueryError, QueryResult, QueryStatus, Result};
+use futures::future::{select, Either};
+use std::collections::VecDeque;
+use std::env;
+use std::io::Write;
+use std::process::{ExitStatus, Stdio};
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// Prefix of the line on which a child process reports its result to the parent
+const RESULT_PREFIX: &str = "SQLBENCH_RESULT ";
+
+/// Number of stderr lines of a crashed child process kept in the results
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Which unit of work runs in a separate process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Isolation {
+    /// Run all queries in the runner process
+    None,
+    /// Run each query in a child process
+    Query,
+    /// Run each iteration of each query in a child process
+    Iteration,
+}
+
+impl FromStr for Isolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Isolation::None),
+            "query" => Ok(Isolation::Query),
+            "iteration" => Ok(Isolation::Iteration),
+            _ => Err(format!("Unsupported isolation: {}", s)),
+        }
+    }
+}
+
+/// Report the result of a query from a child process to the parent
+pub fn report(query_result: &QueryResult) -> Result<()> {
+    let json = serde_json::to_string(query_result)?;
+    let mut stdout = std::io::stdout();
+    writeln!(stdout, "{}{}", RESULT_PREFIX, json)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Run a query in child processes of this binary, one for the whole query or one per iteration,
+/// and record crashes of the child processes. When the runner is interrupted, the interrupt is
+/// forwarded to the running child process.
+pub async fn run_isolated(opt: &Opt, query: &str, interrupt: &Interrupt) -> Result<QueryResult> {
+    if opt.isolation != Isolation::Iteration {
+        return run_child(query, None, interrupt).await;
+    }
+    let mut query_result = QueryResult::new(query.to_string(), vec![], vec![]);
+    for iteration in 0..opt.warmup as u32 + opt.iterations as u32 {
+        if interrupt.is_interrupted() {
+            query_result.status = QueryStatus::Interrupted;
+            break;
+        }
+        let iteration_result = run_child(query, Some(iteration), interrupt).await?;
+        let status = iteration_result.status;
+        merge(&mut query_result, iteration_result);
+        if status != QueryStatus::Ok {
+            break;
+        }
+    }
+    Ok(query_result)
+}
+
+/// Add the result of one iteration to the result of the query
+fn merge(query_result: &mut QueryResult, iteration_result: QueryResult) {
+    query_result
+        .warmup_times
+        .extend(iteration_result.warmup_times);
+    query_result.times.extend(iteration_result.times);
+    query_result
+        .phase_times
+        .extend(iteration_result.phase_times);
+    query_result.metrics.extend(iteration_result.metrics);
+    query_result.statistics = Statistics::from_times(&query_result.times);
+    query_result.rows = query_result.rows.or(iteration_result.rows);
+    query_result.bytes = query_result.bytes.or(iteration_result.bytes);
+    query_result.validation = query_result
+        .validation
+        .take()
+        .or(iteration_result.validation);
+    query_result.status = iteration_result.status;
+    query_result.error = iteration_result.error;
+    query_result.crash = iteration_result.crash;
+}
+
+/// Run a query, or one iteration of it, in a child process with the same arguments. The output
+/// of the child is passed through, and the last lines of its stderr are kept in case it crashes.
+/// When the runner is interrupted, the child is interrupted too, so that it cancels its query
+/// and reports it as interrupted.
+async fn run_child(
+    query: &str,
+    iteration: Option<u32>,
+    interrupt: &Interrupt,
+) -> Result<QueryResult> {
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .arg("--child-query")
+        .arg(query);
+    if let Some(iteration) = iteration {
+        command.arg("--child-iteration").arg(iteration.to_string());
+    }
+    // in its own process group, Ctrl-C in a terminal reaches the child only once, through the
+    // runner, rather than twice, which would make it exit without reporting
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    interrupt.set_child(child.id());
+
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+    let stderr_reader = tokio::spawn(async move {
+        let mut tail = VecDeque::new();
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            eprintln!("{}", line);
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        tail
+    });
+
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut query_result = None;
+    let mut wait_interrupt = interrupt.clone();
+    let mut interrupted = Box::pin(async move { wait_interrupt.wait().await });
+    let mut forwarded = false;
+    loop {
+        let line = if forwarded {
+            lines.next_line().await?
+        } else {
+            match select(Box::pin(lines.next_line()), &mut interrupted).await {
+                Either::Left((line, _)) => line?,
+                Either::Right(_) => {
+                    forwarded = true;
+                    if let Err(e) = child.id().map(interrupt_process).unwrap_or(Ok(())) {
+                        println!("Warning! Failed to interrupt query process: {}", e);
+                        child.start_kill()?;
+                    }
+                    continue;
+                }
+            }
+        };
+        let line = match line {
+            Some(line) => line,
+            None => break,
+        };
+        match line.strip_prefix(RESULT_PREFIX) {
+            Some(json) => query_result = Some(serde_json::from_str::<QueryResult>(json)?),
+            None => println!("{}", line),
+        }
+    }
+    let exit_status = child.wait().await;
+    interrupt.set_child(None);
+    let exit_status = exit_status?;
+    let stderr_tail = stderr_reader.await?;
+
+    match query_result {
+        Some(query_result) => Ok(query_result),
+        None => {
+            let crash = Crash {
+                exit_code: exit_status.code(),
+                signal: signal(&exit_status),
+                stderr_tail: stderr_tail.into_iter().collect(),
+            };
+            let message = match (crash.signal, crash.exit_code) {
+                (Some(signal), _) => format!("Query process was killed by signal {}", signal),
+                (_, Some(code)) => format!("Query process exited with code {}", code),
+                _ => "Query process exited".to_string(),
+            };
+            println!("Query {} crashed: {}", query, message);
+            let error = QueryError::new(ErrorCategory::Execution, message);
+            let mut query_result =
+                QueryResult::incomplete(query.to_string(), QueryStatus::Crashed, Some(error));
+            query_result.crash = Some(crash);
+            Ok(query_result)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal(exit_status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    exit_status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_exit_status: &ExitStatus) -> Option<i32> {
+    None
+}
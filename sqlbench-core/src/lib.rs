@@ -0,0 +1,262 @@
+//! Engine-agnostic parts of the SQLBench runners: command line options, the results model,
+//! query discovery, output writing and comparison of results files. Each query engine
+//! implements the [`Engine`] trait and hands it to [`run`].
+
+mod compare;
+mod config;
+mod engine;
+mod events;
+mod interrupt;
+mod isolation;
+mod manifest;
+mod metrics;
+mod query;
+mod results;
+mod runner;
+mod stats;
+mod status;
+mod table;
+mod validate;
+
+use query::parse_timeout;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use table::{parse_delimiter, PartitionColumnArg};
+
+pub use compare::{compare, CompareOpt};
+pub use config::read_config_file;
+pub use engine::{Engine, OutputSummary, ResultMode};
+pub use events::{Event, EventLog, EVENT_LOG};
+pub use isolation::Isolation;
+pub use manifest::{ColumnManifest, FormatOptions, Manifest, TableManifest};
+pub use metrics::{OperatorMetrics, QueryMetrics};
+pub use query::{
+    discover_queries, natural_cmp, read_query, select_queries, split_statements, Query,
+    QuerySource, QueryTimeoutArg,
+};
+pub use results::{PhaseTimes, QueryResult, Results};
+pub use runner::{execute_query, run};
+pub use stats::Statistics;
+pub use status::{Crash, ErrorCategory, QueryError, QueryStatus, TimeoutError};
+pub use table::{Column, Compression, CsvOptions, FileFormat, SortColumn, Table};
+pub use validate::{Validation, ValidationStatus};
+
+/// Error type used by the shared runner. Engine errors are boxed so that the runner doesn't
+/// depend on any particular engine.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "sqlbench")]
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    /// Run benchmark queries
+    Run(Opt),
+    /// Compare results files for regressions
+    Compare(CompareOpt),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Opt {
+    /// Query engine to benchmark
+    #[structopt(
+        long,
+        default_value = "datafusion",
+        possible_values = &["datafusion", "ballista-standalone", "ballista-remote"]
+    )]
+    pub engine: String,
+
+    /// Host of the Ballista scheduler (ballista-remote)
+    #[structopt(long, default_value = "localhost")]
+    pub scheduler_host: String,
+
+    /// Port of the Ballista scheduler (ballista-remote)
+    #[structopt(long, default_value = "50050")]
+    pub scheduler_port: u16,
+
+    /// Number of in-process executors (ballista-standalone)
+    #[structopt(long, default_value = "1")]
+    pub executors: usize,
+
+    /// Task slots per in-process executor (ballista-standalone). Defaults to the concurrency.
+    #[structopt(long)]
+    pub task_slots: Option<usize>,
+
+    /// Activate debug mode
+    #[structopt(long)]
+    pub debug: bool,
+
+    /// Optional path to config file
+    #[structopt(short, long, parse(from_os_str))]
+    pub config_path: Option<PathBuf>,
+
+    /// Path to queries: a directory with one file per query, a SQL file with one query per line
+    /// (or separated by `--query-separator` lines), or a YAML file mapping query names to SQL
+    #[structopt(long, parse(from_os_str))]
+    pub query_path: PathBuf,
+
+    /// Marker that starts each query in a single-file query suite, e.g. `-- query`. Text after
+    /// the marker on the same line names the query.
+    #[structopt(long, allow_hyphen_values = true)]
+    pub query_separator: Option<String>,
+
+    /// Path to data
+    #[structopt(short, long, parse(from_os_str))]
+    pub data_path: PathBuf,
+
+    /// Optional YAML manifest declaring the tables to register. When specified, only these tables
+    /// are registered and the data directory is not scanned.
+    #[structopt(long, parse(from_os_str))]
+    pub tables: Option<PathBuf>,
+
+    /// Delimiter of .csv files
+    #[structopt(long, default_value = ",", parse(try_from_str = parse_delimiter))]
+    pub csv_delimiter: u8,
+
+    /// .csv files have no header row
+    #[structopt(long)]
+    pub csv_no_header: bool,
+
+    /// Compression of .csv files (uncompressed, gzip, bzip2, xz or zstd)
+    #[structopt(long, default_value = "uncompressed")]
+    pub csv_compression: Compression,
+
+    /// Delimiter of .tbl files (dbgen output, which has no header row)
+    #[structopt(long, default_value = "|", parse(try_from_str = parse_delimiter))]
+    pub tbl_delimiter: u8,
+
+    /// Compression of .tbl files (uncompressed, gzip, bzip2, xz or zstd)
+    #[structopt(long, default_value = "uncompressed")]
+    pub tbl_compression: Compression,
+
+    /// Compression of .json (newline-delimited JSON) files (uncompressed, gzip, bzip2, xz or zstd)
+    #[structopt(long, default_value = "uncompressed")]
+    pub json_compression: Compression,
+
+    /// Hive partition column of a directory table as `table.column[:type]`, e.g.
+    /// `lineitem.l_shipdate:Date32`. Can be repeated. Partition columns that are not declared
+    /// are inferred from the directory names as `Utf8` columns.
+    #[structopt(long)]
+    pub partition_col: Vec<PartitionColumnArg>,
+
+    /// Output path
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: PathBuf,
+
+    /// Also write query results in Parquet format
+    #[structopt(long)]
+    pub output_parquet: bool,
+
+    /// Pattern of the query file names in the query directory, where `*` matches any
+    /// characters. Queries are named after their files, e.g. `q14a` for `q14a.sql`.
+    #[structopt(long, default_value = "q*.sql")]
+    pub query_pattern: String,
+
+    /// Queries to run, as comma-separated names, numbers or ranges, e.g. `q1,q14a,20-22`. A
+    /// number `N` refers to query `qN`. All queries are run when not specified.
+    #[structopt(short, long, use_delimiter = true)]
+    pub query: Vec<String>,
+
+    /// Only run queries whose names match this regular expression
+    #[structopt(long)]
+    pub query_regex: Option<String>,
+
+    /// Queries to exclude, as comma-separated names, numbers or ranges
+    #[structopt(short, long, use_delimiter = true)]
+    pub exclude: Vec<String>,
+
+    /// Timeout in seconds for each iteration of a query. Queries that time out are cancelled
+    /// and recorded as timed out, and the run continues with the next query.
+    #[structopt(long, parse(try_from_str = parse_timeout))]
+    pub query_timeout: Option<f64>,
+
+    /// Timeout in seconds for a specific query as `query=seconds`, e.g. `q21=600`, overriding
+    /// `--query-timeout`. Can be repeated.
+    #[structopt(long)]
+    pub query_timeout_override: Vec<QueryTimeoutArg>,
+
+    /// Run each query (`query`) or each iteration of each query (`iteration`) in a separate
+    /// process, so that a crash is recorded and the run continues with the next query
+    #[structopt(
+        long,
+        default_value = "none",
+        possible_values = &["none", "query", "iteration"]
+    )]
+    pub isolation: Isolation,
+
+    /// Query to run in an isolated child process
+    #[structopt(long, hidden = true)]
+    pub child_query: Option<String>,
+
+    /// Iteration to run in an isolated child process
+    #[structopt(long, hidden = true)]
+    pub child_iteration: Option<u32>,
+
+    /// Concurrency
+    #[structopt(long)]
+    pub concurrency: u8,
+
+    /// How results are consumed: `collect` keeps all batches in memory, `stream` drops batches
+    /// as they arrive and `count` counts rows and bytes as they arrive. Results are only
+    /// written and validated in `collect` mode.
+    #[structopt(
+        long,
+        default_value = "collect",
+        possible_values = &["collect", "stream", "count"]
+    )]
+    pub result_mode: ResultMode,
+
+    /// Iterations (number of times to run each query)
+    #[structopt(short, long)]
+    pub iterations: u8,
+
+    /// Warmup iterations to run before the measured iterations. Their timings are recorded
+    /// separately and excluded from the statistics.
+    #[structopt(long, default_value = "0")]
+    pub warmup: u8,
+
+    /// Optional path to expected answers (`q1.csv`, `q1.out`, ...) to validate query results
+    /// against
+    #[structopt(long, parse(from_os_str))]
+    pub expected_path: Option<PathBuf>,
+
+    /// Maximum absolute difference between numeric values when validating query results
+    #[structopt(long, default_value = "0.01")]
+    pub float_tolerance: f64,
+
+    /// Directory of a previous run to resume. Queries that completed in that run are copied from
+    /// its event log and not run again. Missing and failed queries are run.
+    #[structopt(long, parse(from_os_str))]
+    pub resume: Option<PathBuf>,
+
+    /// Optional GitHub SHA of DataFusion version for inclusion in result yaml file
+    #[structopt(short, long)]
+    pub rev: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_separator_starting_with_hyphens() {
+        let opt = Opt::from_iter_safe([
+            "sqlbench",
+            "--query-path",
+            "queries.sql",
+            "--query-separator",
+            "-- query",
+            "--data-path",
+            "data",
+            "--output",
+            "out",
+            "--concurrency",
+            "1",
+            "--iterations",
+            "1",
+        ])
+        .unwrap();
+        assert_eq!(opt.query_separator.as_deref(), Some("-- query"));
+    }
+}
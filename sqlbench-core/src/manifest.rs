@@ -0,0 +1,347 @@
+use crate::table::{parse_delimiter, Column, Compression, CsvOptions, FileFormat, SortColumn};
+use crate::{Result, Table};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+/// Declarative list of the tables to register, read from a YAML file.
+///
+/// ```yaml
+/// tables:
+///   - name: lineitem
+///     path: lineitem/
+///     format: parquet
+///     partition_cols:
+///       - name: l_shipdate
+///         type: Date32
+///     sort_order: [l_orderkey, l_linenumber DESC]
+///   - name: nation
+///     paths: [nation.tbl]
+///     format: tbl
+///     schema:
+///       - { name: n_nationkey, type: Int64, nullable: false }
+///       - { name: n_name, type: Utf8 }
+///       - { name: n_regionkey, type: Int64 }
+///       - { name: n_comment, type: Utf8 }
+///     options:
+///       delimiter: "|"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub tables: Vec<TableManifest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableManifest {
+    pub name: String,
+    /// Path to a file or directory. Relative paths are relative to the data path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Paths to files or directories, for tables made of several paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// One of parquet, csv, tbl, json, avro or arrow
+    pub format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Vec<ColumnManifest>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition_cols: Vec<ColumnManifest>,
+    /// Columns the files are sorted by, e.g. `l_orderkey` or `l_orderkey DESC NULLS LAST`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort_order: Vec<String>,
+    #[serde(default)]
+    pub options: FormatOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnManifest {
+    pub name: String,
+    /// Arrow data type, e.g. `Int64`, `Utf8` or `Decimal128(15, 2)`
+    #[serde(rename = "type")]
+    pub data_type: String,
+    #[serde(default = "default_nullable")]
+    pub nullable: bool,
+}
+
+fn default_nullable() -> bool {
+    true
+}
+
+/// Options for csv, tbl and json tables
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FormatOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_header: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// File extension of files in directories, not including the compression extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_extension: Option<String>,
+    /// Each line ends with a delimiter, as in dbgen output. Defaults to true for tbl tables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailing_delimiter: Option<bool>,
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        serde_yaml::from_reader(file)
+            .map_err(|e| format!("Invalid table manifest {}: {}", path.display(), e).into())
+    }
+
+    /// Tables declared in the manifest, with relative paths resolved against the data path
+    pub fn tables(&self, data_path: &Path) -> Result<Vec<Table>> {
+        self.tables
+            .iter()
+            .map(|table| {
+                table
+                    .to_table(data_path)
+                    .map_err(|e| format!("Table {}: {}", table.name, e).into())
+            })
+            .collect()
+    }
+}
+
+impl TableManifest {
+    fn to_table(&self, data_path: &Path) -> Result<Table> {
+        let paths = self
+            .path
+            .iter()
+            .chain(self.paths.iter())
+            .map(|path| format!("{}", data_path.join(path).display()))
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return Err("no path declared".into());
+        }
+        let compression = match &self.options.compression {
+            Some(compression) => compression.parse::<Compression>()?,
+            None => Compression::Uncompressed,
+        };
+        let delimiter = |default| match &self.options.delimiter {
+            Some(delimiter) => parse_delimiter(delimiter),
+            None => Ok(default),
+        };
+        let file_extension = |default: &str| {
+            self.options
+                .file_extension
+                .clone()
+                .unwrap_or_else(|| default.to_string())
+        };
+        let format = match self.format.as_str() {
+            "parquet" => FileFormat::Parquet,
+            "csv" => FileFormat::Csv(CsvOptions {
+                delimiter: delimiter(b',')?,
+                has_header: self.options.has_header.unwrap_or(true),
+                compression,
+                file_extension: file_extension(".csv"),
+                trailing_delimiter: self.options.trailing_delimiter.unwrap_or(false),
+            }),
+            "tbl" => FileFormat::Csv(CsvOptions {
+                delimiter: delimiter(b'|')?,
+                has_header: self.options.has_header.unwrap_or(false),
+                compression,
+                file_extension: file_extension(".tbl"),
+                trailing_delimiter: self.options.trailing_delimiter.unwrap_or(true),
+            }),
+            "json" => FileFormat::Json(compression),
+            "avro" => FileFormat::Avro,
+            "arrow" => FileFormat::Arrow,
+            other => return Err(format!("unsupported format {}", other).into()),
+        };
+        let sort_order = self
+            .sort_order
+            .iter()
+            .map(|s| s.parse::<SortColumn>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Table {
+            name: self.name.clone(),
+            paths,
+            format,
+            schema: self.schema.as_ref().map(|schema| {
+                schema
+                    .iter()
+                    .map(|column| Column {
+                        name: column.name.clone(),
+                        data_type: column.data_type.clone(),
+                        nullable: column.nullable,
+                    })
+                    .collect()
+            }),
+            partition_cols: self
+                .partition_cols
+                .iter()
+                .map(|column| (column.name.clone(), column.data_type.clone()))
+                .collect(),
+            sort_order,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn tpch_manifest() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../sqlbench/tpch-tables.yaml")
+    }
+
+    fn tables(yaml: &str) -> Result<Vec<Table>> {
+        let manifest: Manifest = serde_yaml::from_str(yaml)?;
+        manifest.tables(Path::new("/data"))
+    }
+
+    #[test]
+    fn tpch_tables() -> Result<()> {
+        let tables = Manifest::read(&tpch_manifest())?.tables(Path::new("/data"))?;
+        let names = tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "nation", "region", "part", "supplier", "partsupp", "customer", "orders",
+                "lineitem"
+            ]
+        );
+        let part = &tables[2];
+        assert_eq!(part.paths, vec!["/data/part.tbl"]);
+        assert_eq!(
+            part.format,
+            FileFormat::Csv(CsvOptions {
+                delimiter: b'|',
+                has_header: false,
+                compression: Compression::Uncompressed,
+                file_extension: ".tbl".to_string(),
+                trailing_delimiter: true,
+            })
+        );
+        let schema = part.schema.as_ref().unwrap();
+        assert_eq!(schema.len(), 9);
+        assert_eq!(
+            schema[7],
+            Column {
+                name: "p_retailprice".to_string(),
+                data_type: "Decimal128(15, 2)".to_string(),
+                nullable: true,
+            }
+        );
+        assert_eq!(tables[7].schema.as_ref().unwrap().len(), 16);
+        assert!(tables.iter().all(|t| t.partition_cols.is_empty()));
+        assert!(tables.iter().all(|t| t.sort_order.is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn partition_cols_and_sort_order() -> Result<()> {
+        let tables = tables(
+            r#"
+tables:
+  - name: lineitem
+    path: lineitem/
+    format: parquet
+    partition_cols:
+      - name: l_shipdate
+        type: Date32
+    sort_order: [l_orderkey, l_linenumber DESC, l_comment asc nulls first]
+"#,
+        )?;
+        let lineitem = &tables[0];
+        assert_eq!(lineitem.format, FileFormat::Parquet);
+        assert_eq!(lineitem.schema, None);
+        assert_eq!(
+            lineitem.partition_cols,
+            vec![("l_shipdate".to_string(), "Date32".to_string())]
+        );
+        let sort_column = |name: &str, descending, nulls_first| SortColumn {
+            name: name.to_string(),
+            descending,
+            nulls_first,
+        };
+        assert_eq!(
+            lineitem.sort_order,
+            vec![
+                sort_column("l_orderkey", false, false),
+                sort_column("l_linenumber", true, true),
+                sort_column("l_comment", false, true),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn format_options() -> Result<()> {
+        let tables = tables(
+            r#"
+tables:
+  - name: orders
+    paths: [orders/1.csv.gz, orders/2.csv.gz]
+    format: csv
+    schema:
+      - { name: o_orderkey, type: Int64, nullable: false }
+    options:
+      delimiter: "\\t"
+      has_header: false
+      compression: gzip
+"#,
+        )?;
+        let orders = &tables[0];
+        assert_eq!(
+            orders.paths,
+            vec!["/data/orders/1.csv.gz", "/data/orders/2.csv.gz"]
+        );
+        assert_eq!(
+            orders.format,
+            FileFormat::Csv(CsvOptions {
+                delimiter: b'\t',
+                has_header: false,
+                compression: Compression::Gzip,
+                file_extension: ".csv".to_string(),
+                trailing_delimiter: false,
+            })
+        );
+        assert!(!orders.schema.as_ref().unwrap()[0].nullable);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_fields() {
+        for yaml in [
+            "tables: []\nviews: []",
+            "tables:\n  - { name: t, path: t.tbl, format: tbl, sort: [a] }",
+            "tables:\n  - { name: t, path: t.tbl, format: tbl, options: { delimeter: ',' } }",
+            "tables:\n  - { name: t, path: t.tbl, format: tbl, schema: [{ name: a, typ: Int64 }] }",
+        ] {
+            let err = serde_yaml::from_str::<Manifest>(yaml).unwrap_err();
+            assert!(err.to_string().contains("unknown field"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn invalid_tables() {
+        for (yaml, expected) in [
+            (
+                "tables: [{ name: t, format: tbl }]",
+                "Table t: no path declared",
+            ),
+            (
+                "tables: [{ name: t, path: t.orc, format: orc }]",
+                "Table t: unsupported format orc",
+            ),
+            (
+                "tables: [{ name: t, path: t, format: parquet, sort_order: [a DESC LAST] }]",
+                "Table t: Invalid sort column: a DESC LAST",
+            ),
+            (
+                "tables: [{ name: t, path: t, format: csv, options: { compression: lz4 } }]",
+                "Table t: Unsupported compression: lz4",
+            ),
+        ] {
+            assert_eq!(tables(yaml).unwrap_err().to_string(), expected);
+        }
+    }
+}
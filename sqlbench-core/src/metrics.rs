@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Metrics of one operator in an executed physical plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OperatorMetrics {
+    /// Operator type, e.g. `ParquetExec` or `HashJoinExec`
+    pub operator: String,
+    /// Depth of the operator in the plan, with the root at depth 0
+    pub depth: usize,
+    /// Metric values summed over all partitions, keyed by metric name, e.g. `output_rows`.
+    /// Times are in nanoseconds.
+    pub metrics: BTreeMap<String, usize>,
+}
+
+impl OperatorMetrics {
+    fn get(&self, name: &str) -> usize {
+        self.metrics.get(name).copied().unwrap_or(0)
+    }
+}
+
+/// Metrics of an executed query, per operator and summarized over the whole plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct QueryMetrics {
+    /// Operators in plan order, parents before their children
+    pub operators: Vec<OperatorMetrics>,
+    /// Compute time in nanoseconds, summed per operator type
+    pub compute_time_by_operator: BTreeMap<String, usize>,
+    /// Bytes read from Parquet files
+    pub parquet_bytes_scanned: usize,
+    /// Parquet row groups skipped using statistics or bloom filters
+    pub parquet_row_groups_pruned: usize,
+    /// Rows filtered out by Parquet predicate pushdown
+    pub parquet_rows_filtered: usize,
+    /// Rows on the build side of hash joins
+    pub hash_join_build_rows: usize,
+    /// Memory used by the hash tables of hash joins, in bytes
+    pub hash_join_build_mem_used: usize,
+    /// Number of times operators spilled to disk
+    pub spill_count: usize,
+    /// Bytes spilled to disk
+    pub spilled_bytes: usize,
+}
+
+impl QueryMetrics {
+    /// Summarize the metrics of the operators of an executed plan
+    pub fn from_operators(operators: Vec<OperatorMetrics>) -> Self {
+        let sum = |operator: Option<&str>, name: &str| -> usize {
+            operators
+                .iter()
+                .filter(|op| operator.map(|o| op.operator == o).unwrap_or(true))
+                .map(|op| op.get(name))
+                .sum()
+        };
+        let mut compute_time_by_operator = BTreeMap::new();
+        for op in &operators {
+            if let Some(time) = op.metrics.get("elapsed_compute") {
+                *compute_time_by_operator
+                    .entry(op.operator.clone())
+                    .or_insert(0) += time;
+            }
+        }
+        Self {
+            compute_time_by_operator,
+            parquet_bytes_scanned: sum(Some("ParquetExec"), "bytes_scanned"),
+            parquet_row_groups_pruned: sum(Some("ParquetExec"), "row_groups_pruned"),
+            parquet_rows_filtered: sum(Some("ParquetExec"), "pushdown_rows_filtered"),
+            hash_join_build_rows: sum(Some("HashJoinExec"), "build_input_rows"),
+            hash_join_build_mem_used: sum(Some("HashJoinExec"), "build_mem_used"),
+            spill_count: sum(None, "spill_count"),
+            spilled_bytes: sum(None, "spilled_bytes"),
+            operators,
+        }
+    }
+}
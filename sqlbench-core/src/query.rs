@@ -0,0 +1,613 @@
+use crate::{ErrorCategory, Opt, QueryError, Result};
+use regex::Regex;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A benchmark query, e.g. `q1` or `q14a`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub name: String,
+    pub sql: String,
+    /// Where the query was read from, e.g. `q1.sql` or `queries.sql:12`
+    pub origin: String,
+}
+
+/// Where the queries of a benchmark suite are read from
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuerySource {
+    /// A directory with one file per query, named after the file
+    Directory { path: PathBuf, pattern: String },
+    /// A single SQL file with one query per line, such as ClickBench's `queries.sql`, or with
+    /// queries separated by marker lines. Queries are named `q1`, `q2`, ... in file order,
+    /// unless a marker line names the query, e.g. `-- query q14a`.
+    File {
+        path: PathBuf,
+        separator: Option<String>,
+    },
+    /// A YAML file mapping query names to SQL
+    Yaml { path: PathBuf },
+}
+
+impl QuerySource {
+    /// The query source for `--query-path`, depending on whether it is a directory, a YAML file
+    /// or a SQL file
+    pub fn new(opt: &Opt) -> Self {
+        let path = opt.query_path.clone();
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        if path.is_dir() {
+            QuerySource::Directory {
+                path,
+                pattern: opt.query_pattern.clone(),
+            }
+        } else if matches!(extension, Some("yaml") | Some("yml")) {
+            QuerySource::Yaml { path }
+        } else {
+            QuerySource::File {
+                path,
+                separator: opt.query_separator.clone(),
+            }
+        }
+    }
+
+    /// Read the queries. Queries in a directory are in natural order (`q2` before `q10`, `q14`
+    /// before `q14a`) and queries in a file are in file order. Query names must be unique.
+    pub fn queries(&self) -> Result<Vec<Query>> {
+        let (path, queries) = match self {
+            QuerySource::Directory { path, pattern } => (path, directory_queries(path, pattern)?),
+            QuerySource::File { path, separator } => {
+                (path, file_queries(path, separator.as_deref())?)
+            }
+            QuerySource::Yaml { path } => (path, yaml_queries(path)?),
+        };
+        if queries.is_empty() {
+            return Err(format!("No queries found in {}", path.display()).into());
+        }
+        let mut names = HashSet::new();
+        for query in &queries {
+            if !names.insert(&query.name) {
+                return Err(format!("Duplicate query {} in {}", query.name, path.display()).into());
+            }
+        }
+        Ok(queries)
+    }
+}
+
+/// A per-query timeout declared on the command line as `query=seconds`, e.g. `q21=600`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTimeoutArg {
+    pub query: String,
+    pub seconds: f64,
+}
+
+impl FromStr for QueryTimeoutArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("Query timeout must be declared as query=seconds: {}", s);
+        let (query, seconds) = s.split_once('=').ok_or_else(invalid)?;
+        Ok(Self {
+            query: query.trim().to_string(),
+            seconds: parse_timeout(seconds)?,
+        })
+    }
+}
+
+/// Parse a timeout in seconds, which must be a positive number
+pub fn parse_timeout(s: &str) -> std::result::Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(seconds),
+        _ => Err(format!(
+            "Timeout must be a positive number of seconds: {}",
+            s
+        )),
+    }
+}
+
+/// Check that the queries of the timeout overrides exist, so that a misspelled query doesn't
+/// silently run without its timeout
+pub fn check_timeout_overrides(opt: &Opt, queries: &[Query]) -> Result<()> {
+    for arg in &opt.query_timeout_override {
+        if !queries.iter().any(|q| is_query(&arg.query, &q.name)) {
+            return Err(
+                format!("Query {} of --query-timeout-override not found", arg.query).into(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Whether a query name given on the command line refers to a query. Plain numbers refer to
+/// queries named `q{N}`, so `14` is the same as `q14`.
+pub fn is_query(arg: &str, name: &str) -> bool {
+    arg == name || (arg.chars().all(|c| c.is_ascii_digit()) && name == format!("q{}", arg))
+}
+
+/// Read the queries of the suite from `--query-path`
+pub fn discover_queries(opt: &Opt) -> Result<Vec<Query>> {
+    QuerySource::new(opt).queries()
+}
+
+/// Queries in a directory whose file names match the pattern
+fn directory_queries(dir: &Path, pattern: &str) -> Result<Vec<Query>> {
+    let mut queries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let filename = match Path::file_name(&path).and_then(|f| f.to_str()) {
+            Some(filename) if path.is_file() => filename,
+            _ => continue,
+        };
+        if !matches_pattern(pattern, filename) {
+            continue;
+        }
+        let name = match filename.rsplit_once('.') {
+            Some((name, _)) => name,
+            None => filename,
+        };
+        queries.push(Query {
+            name: name.to_string(),
+            sql: fs::read_to_string(&path)?,
+            origin: format!("{}", path.display()),
+        });
+    }
+    queries.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    Ok(queries)
+}
+
+/// Queries in a single SQL file, one per line or separated by marker lines. Blank lines and
+/// `--` comment lines between queries are skipped in one-query-per-line files, and comments
+/// before the first marker line, such as a licence header, are skipped in files with markers.
+/// Unnamed queries are named after their position in the file.
+fn file_queries(path: &Path, separator: Option<&str>) -> Result<Vec<Query>> {
+    let contents = fs::read_to_string(path)?;
+    // (name, first line, SQL) of each query
+    let mut parts: Vec<(Option<String>, usize, String)> = vec![];
+    match separator {
+        None => {
+            for (i, line) in contents.lines().enumerate() {
+                let sql = line.trim();
+                if !sql.is_empty() && !sql.starts_with("--") {
+                    parts.push((None, i + 1, sql.to_string()));
+                }
+            }
+        }
+        Some(separator) => {
+            // (name, first line, lines) of the query being read
+            let mut current: Option<(Option<String>, usize, Vec<&str>)> = None;
+            let mut preamble = false;
+            for (i, line) in contents.lines().enumerate() {
+                if let Some(rest) = line.trim().strip_prefix(separator) {
+                    if let Some((name, start, lines)) = current.take() {
+                        parts.push((name, start, lines.join("\n")));
+                    }
+                    let name = Some(rest.trim()).filter(|n| !n.is_empty());
+                    current = Some((name.map(|n| n.to_string()), i + 2, vec![]));
+                } else if let Some((_, _, lines)) = current.as_mut() {
+                    lines.push(line);
+                } else if !line.trim().is_empty() {
+                    // a query, or only comments, before the first marker
+                    current = Some((None, i + 1, vec![line]));
+                    preamble = true;
+                }
+            }
+            if let Some((name, start, lines)) = current {
+                parts.push((name, start, lines.join("\n")));
+            }
+            if preamble && is_comment_only(&parts[0].2) {
+                parts.remove(0);
+            }
+        }
+    }
+    parts.retain(|(_, _, sql)| !sql.trim().is_empty());
+    let queries = parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, line, sql))| Query {
+            name: name.unwrap_or_else(|| format!("q{}", i + 1)),
+            sql,
+            origin: format!("{}:{}", path.display(), line),
+        })
+        .collect();
+    Ok(queries)
+}
+
+/// Whether SQL has only comments and whitespace
+fn is_comment_only(sql: &str) -> bool {
+    split_statements(sql)
+        .map(|statements| statements.is_empty())
+        .unwrap_or(false)
+}
+
+/// Queries in a YAML file mapping query names to SQL, in file order
+fn yaml_queries(path: &Path) -> Result<Vec<Query>> {
+    let file = fs::File::open(path)?;
+    let mapping: serde_yaml::Mapping = serde_yaml::from_reader(file)
+        .map_err(|e| format!("Invalid query file {}: {}", path.display(), e))?;
+    mapping
+        .into_iter()
+        .map(|(name, sql)| {
+            let name = match name {
+                serde_yaml::Value::String(name) => name,
+                serde_yaml::Value::Number(n) => format!("q{}", n),
+                other => return Err(format!("Invalid query name {:?}", other).into()),
+            };
+            let sql = match sql {
+                serde_yaml::Value::String(sql) => sql,
+                _ => return Err(format!("Query {} is not a SQL string", name).into()),
+            };
+            Ok(Query {
+                origin: format!("{} ({})", path.display(), name),
+                name,
+                sql,
+            })
+        })
+        .collect()
+}
+
+/// The queries selected with `--query` and `--query-regex`, or all queries
+pub fn select_queries(opt: &Opt, queries: Vec<Query>) -> Result<Vec<Query>> {
+    let mut selected = if opt.query.is_empty() {
+        queries
+    } else {
+        let names = resolve(&opt.query, &queries)?;
+        queries
+            .into_iter()
+            .filter(|q| names.contains(&q.name))
+            .collect()
+    };
+    if let Some(regex) = &opt.query_regex {
+        let regex = Regex::new(regex)?;
+        selected.retain(|q| regex.is_match(&q.name));
+    }
+    Ok(selected)
+}
+
+/// The names of the queries excluded with `--exclude`
+pub fn excluded_queries(opt: &Opt, queries: &[Query]) -> Result<Vec<String>> {
+    resolve(&opt.exclude, queries)
+}
+
+/// Resolve a list of query names and inclusive ranges such as `q1-q10` to query names
+fn resolve(args: &[String], queries: &[Query]) -> Result<Vec<String>> {
+    let find = |arg: &str| {
+        queries
+            .iter()
+            .position(|q| is_query(arg, &q.name))
+            .ok_or_else(|| format!("Query {} not found", arg))
+    };
+    let mut names = vec![];
+    for arg in args.iter().map(|arg| arg.trim()) {
+        if let Ok(i) = find(arg) {
+            names.push(queries[i].name.clone());
+            continue;
+        }
+        match arg.split_once('-') {
+            Some((from_arg, to_arg)) => {
+                let (from, to) = (find(from_arg)?, find(to_arg)?);
+                if from > to {
+                    return Err(format!(
+                        "Invalid query range {}: {} comes after {}",
+                        arg, from_arg, to_arg
+                    )
+                    .into());
+                }
+                names.extend(queries[from..=to].iter().map(|q| q.name.clone()));
+            }
+            None => return Err(find(arg).unwrap_err().into()),
+        }
+    }
+    Ok(names)
+}
+
+/// Match a file name against a pattern where `*` matches any characters and `?` matches one
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Compare names so that runs of digits compare as numbers, e.g. `q2` < `q10` < `q10a`
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_len = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+                let y_len = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+                let x_digits = a[..x_len].trim_start_matches('0');
+                let y_digits = b[..y_len].trim_start_matches('0');
+                let ordering = x_digits
+                    .len()
+                    .cmp(&y_digits.len())
+                    .then_with(|| x_digits.cmp(y_digits));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a = &a[x_len..];
+                b = &b[y_len..];
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// Read the SQL statements for a query. Some queries have multiple statements, and a query
+/// without any, e.g. with only comments, is an error rather than a query that takes no time.
+pub fn read_query(query: &Query) -> Result<Vec<String>> {
+    println!("Executing query {} from {}", query.name, query.origin);
+    let statements = split_statements(&query.sql).map_err(|e| {
+        let message = format!(
+            "Failed to split query {} into statements: {}",
+            query.name, e
+        );
+        QueryError::new(ErrorCategory::SqlParse, message)
+    })?;
+    if statements.is_empty() {
+        let message = format!("Query {} has no statements", query.name);
+        return Err(QueryError::new(ErrorCategory::SqlParse, message).into());
+    }
+    Ok(statements)
+}
+
+/// Split SQL into statements at the semicolons that are not in comments, string literals or
+/// quoted identifiers. Each statement keeps its original text, without the semicolon, and parts
+/// with only whitespace and comments are dropped.
+pub fn split_statements(sql: &str) -> Result<Vec<String>> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, sql).tokenize_with_location()?;
+
+    // byte offset of the start of each line, as token locations are lines and columns
+    let line_starts = std::iter::once(0)
+        .chain(sql.match_indices('\n').map(|(i, _)| i + 1))
+        .collect::<Vec<_>>();
+    let offset = |location: &Location| {
+        let line_start = line_starts[location.line as usize - 1];
+        sql[line_start..]
+            .char_indices()
+            .nth(location.column as usize - 1)
+            .map(|(i, _)| line_start + i)
+            .unwrap_or(sql.len())
+    };
+
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut has_tokens = false;
+    for token in &tokens {
+        match token.token {
+            Token::SemiColon => {
+                let end = offset(&token.location);
+                if has_tokens {
+                    statements.push(sql[start..end].to_string());
+                }
+                start = end + 1;
+                has_tokens = false;
+            }
+            Token::Whitespace(_) | Token::EOF => {}
+            _ => has_tokens = true,
+        }
+    }
+    if has_tokens {
+        statements.push(sql[start..].to_string());
+    }
+    Ok(statements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries(names: &[&str]) -> Vec<Query> {
+        names
+            .iter()
+            .map(|name| Query {
+                name: name.to_string(),
+                sql: String::new(),
+                origin: format!("{}.sql", name),
+            })
+            .collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn split_at_semicolons() {
+        let statements =
+            split_statements("create view v as select 1;\nselect * from v;\n").unwrap();
+        assert_eq!(
+            statements,
+            vec!["create view v as select 1", "\nselect * from v"]
+        );
+    }
+
+    #[test]
+    fn split_without_trailing_semicolon() {
+        assert_eq!(split_statements("select 1").unwrap(), vec!["select 1"]);
+    }
+
+    #[test]
+    fn split_ignores_semicolon_in_string_literal() {
+        let sql = "select * from t where s like '%;%'; select 2";
+        assert_eq!(
+            split_statements(sql).unwrap(),
+            vec!["select * from t where s like '%;%'", " select 2"]
+        );
+    }
+
+    #[test]
+    fn split_ignores_semicolon_in_quoted_identifier() {
+        let sql = "select \"a;b\" from t";
+        assert_eq!(split_statements(sql).unwrap(), vec![sql]);
+    }
+
+    #[test]
+    fn split_ignores_semicolon_in_comments() {
+        let sql = "select 1 -- first; query\n/* ; */ from t";
+        assert_eq!(split_statements(sql).unwrap(), vec![sql]);
+    }
+
+    #[test]
+    fn split_drops_trailing_comment() {
+        let sql = "select 1;\n-- end of query;\n";
+        assert_eq!(split_statements(sql).unwrap(), vec!["select 1"]);
+    }
+
+    #[test]
+    fn split_handles_multibyte_characters() {
+        let sql = "select 'é';\nselect 'ü'";
+        assert_eq!(
+            split_statements(sql).unwrap(),
+            vec!["select 'é'", "\nselect 'ü'"]
+        );
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["q10", "q14a", "q2", "q14", "q1", "q02b"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["q1", "q2", "q02b", "q10", "q14", "q14a"]);
+        assert_eq!(natural_cmp("q14", "q14a"), Ordering::Less);
+        assert_eq!(natural_cmp("q2", "q10"), Ordering::Less);
+        assert_eq!(natural_cmp("q7", "q7"), Ordering::Equal);
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("q*.sql", "q1.sql"));
+        assert!(matches_pattern("q*.sql", "q14a.sql"));
+        assert!(matches_pattern("q?.sql", "q1.sql"));
+        assert!(!matches_pattern("q?.sql", "q10.sql"));
+        assert!(!matches_pattern("q*.sql", "q1.sql.bak"));
+        assert!(matches_pattern("*a*b", "xaybab"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("?", ""));
+    }
+
+    #[test]
+    fn resolve_names_and_numbers() {
+        let queries = queries(&["q1", "q2", "q14", "q14a"]);
+        assert_eq!(
+            resolve(&args(&["q1", "2", " q14a "]), &queries).unwrap(),
+            args(&["q1", "q2", "q14a"])
+        );
+        assert!(resolve(&args(&["q3"]), &queries).is_err());
+    }
+
+    #[test]
+    fn resolve_ranges() {
+        let queries = queries(&["q1", "q2", "q14", "q14a", "q20"]);
+        assert_eq!(
+            resolve(&args(&["q2-q14a"]), &queries).unwrap(),
+            args(&["q2", "q14", "q14a"])
+        );
+        assert_eq!(
+            resolve(&args(&["1-2"]), &queries).unwrap(),
+            args(&["q1", "q2"])
+        );
+        assert_eq!(
+            resolve(&args(&["q14-q14"]), &queries).unwrap(),
+            args(&["q14"])
+        );
+        assert!(resolve(&args(&["q1-q3"]), &queries).is_err());
+    }
+
+    #[test]
+    fn resolve_rejects_reversed_range() {
+        let queries = queries(&["q1", "q2", "q14", "q14a", "q20"]);
+        assert!(resolve(&args(&["q20-q2"]), &queries).is_err());
+        assert!(resolve(&args(&["q14a-q14"]), &queries).is_err());
+    }
+
+    /// Queries of a query file with the given contents and `-- query` markers
+    fn separated_queries(name: &str, contents: &str) -> Result<Vec<Query>> {
+        let path =
+            std::env::temp_dir().join(format!("sqlbench-{}-{}.sql", std::process::id(), name));
+        fs::write(&path, contents)?;
+        let queries = QuerySource::File {
+            path: path.clone(),
+            separator: Some("-- query".to_string()),
+        }
+        .queries();
+        fs::remove_file(&path)?;
+        queries
+    }
+
+    fn names(queries: &[Query]) -> Vec<&str> {
+        queries.iter().map(|q| q.name.as_str()).collect()
+    }
+
+    #[test]
+    fn separated_queries_skip_comment_preamble() {
+        let contents = concat!(
+            "-- Licensed under the Apache License\n/* header */\n\n",
+            "-- query q1\nselect 1\n-- query\nselect 2\n"
+        );
+        let queries = separated_queries("preamble", contents).unwrap();
+        assert_eq!(names(&queries), vec!["q1", "q2"]);
+        assert_eq!(queries[0].sql, "select 1");
+    }
+
+    #[test]
+    fn separated_queries_keep_query_before_first_marker() {
+        let queries = separated_queries("first", "select 1\n-- query\nselect 2\n").unwrap();
+        assert_eq!(names(&queries), vec!["q1", "q2"]);
+    }
+
+    #[test]
+    fn separated_queries_are_numbered_without_gaps() {
+        let contents =
+            "-- query\n\n-- query\nselect 1\n-- query q14a\nselect 2\n-- query\nselect 3\n";
+        let queries = separated_queries("numbering", contents).unwrap();
+        assert_eq!(names(&queries), vec!["q1", "q14a", "q3"]);
+    }
+
+    #[test]
+    fn separated_queries_reject_duplicate_names() {
+        let contents = "select 1\n-- query q1\nselect 2\n";
+        assert!(separated_queries("duplicate", contents).is_err());
+    }
+
+    #[test]
+    fn query_without_statements() {
+        let query = Query {
+            name: "q1".to_string(),
+            sql: "-- select 1;\n/* select 2; */\n".to_string(),
+            origin: "q1.sql".to_string(),
+        };
+        let error = read_query(&query).unwrap_err();
+        let error = error.downcast_ref::<QueryError>().unwrap();
+        assert_eq!(error.category, ErrorCategory::SqlParse);
+    }
+}
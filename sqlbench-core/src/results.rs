@@ -0,0 +1,235 @@
+use crate::manifest::Manifest;
+use crate::metrics::QueryMetrics;
+use crate::stats::Statistics;
+use crate::status::{Crash, QueryError, QueryStatus};
+use crate::validate::{Validation, ValidationStatus};
+use crate::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Results {
+    pub system_time: u128,
+    pub engine: String,
+    /// Version of the engine crate, e.g. the Ballista version for Ballista runs
+    pub engine_version: String,
+    /// Version of DataFusion the engine was built against
+    pub datafusion_version: String,
+    /// GitHub SHA passed with `--rev`
+    pub datafusion_github_sha: Option<String>,
+    /// Versions and sources of the engine crates the runner was built with, and the runner's
+    /// git commit
+    pub build: BTreeMap<String, String>,
+    /// Versions reported by the cluster, e.g. the Ballista scheduler and executors
+    pub cluster: BTreeMap<String, String>,
+    pub config: HashMap<String, String>,
+    pub command_line_args: Vec<String>,
+    /// Table manifest, when tables were declared with `--tables`
+    pub manifest: Option<Manifest>,
+    pub register_tables_time: u128,
+    pub query_results: Vec<QueryResult>,
+}
+
+/// Timings for one query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct QueryResult {
+    /// Query name. Results files written before queries had names have query numbers, which
+    /// are read as `q{N}`.
+    #[serde(deserialize_with = "query_name")]
+    pub query: String,
+    pub status: QueryStatus,
+    /// Error that made the query fail
+    pub error: Option<QueryError>,
+    /// How the query process crashed, with `--isolation`
+    pub crash: Option<Crash>,
+    /// Duration of each warmup iteration in milliseconds
+    pub warmup_times: Vec<u128>,
+    /// Duration of each measured iteration in milliseconds
+    pub times: Vec<u128>,
+    /// Duration of each phase of each measured iteration
+    pub phase_times: Vec<PhaseTimes>,
+    pub statistics: Statistics,
+    /// Rows returned by the first measured iteration, unless results were streamed
+    pub rows: Option<usize>,
+    /// In-memory size in bytes of the results of the first measured iteration, unless results
+    /// were streamed
+    pub bytes: Option<usize>,
+    /// Result of validating the query output against the expected answer
+    pub validation: Option<Validation>,
+    /// Metrics of the executed physical plan of each statement, from the first measured
+    /// iteration
+    pub metrics: Vec<QueryMetrics>,
+}
+
+/// Durations of the phases of one iteration of a query in milliseconds, summed over all
+/// statements
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PhaseTimes {
+    /// Parsing SQL and creating the logical plan
+    pub parse: f64,
+    /// Optimizing the logical plan
+    pub optimize: f64,
+    /// Creating the physical plan, for engines that create it before execution
+    pub physical_plan: Option<f64>,
+    /// Executing the physical plan
+    pub execute: f64,
+    /// Time from the start of execution to the first result batch, for statements that returned
+    /// a batch
+    pub first_batch: Option<f64>,
+}
+
+impl PhaseTimes {
+    /// Add the phase times of another statement
+    pub fn add(&mut self, other: &PhaseTimes) {
+        self.parse += other.parse;
+        self.optimize += other.optimize;
+        if let Some(physical_plan) = other.physical_plan {
+            self.physical_plan = Some(self.physical_plan.unwrap_or(0.0) + physical_plan);
+        }
+        self.execute += other.execute;
+        if let Some(first_batch) = other.first_batch {
+            self.first_batch = Some(self.first_batch.unwrap_or(0.0) + first_batch);
+        }
+    }
+}
+
+impl QueryResult {
+    pub fn new(query: String, warmup_times: Vec<u128>, times: Vec<u128>) -> Self {
+        let statistics = Statistics::from_times(&times);
+        Self {
+            query,
+            status: QueryStatus::Ok,
+            error: None,
+            crash: None,
+            warmup_times,
+            times,
+            phase_times: vec![],
+            statistics,
+            rows: None,
+            bytes: None,
+            validation: None,
+            metrics: vec![],
+        }
+    }
+
+    /// Result of a query that did not complete
+    pub fn incomplete(query: String, status: QueryStatus, error: Option<QueryError>) -> Self {
+        let mut result = Self::new(query, vec![], vec![]);
+        result.status = status;
+        result.error = error;
+        result
+    }
+
+    /// Whether the query failed, or returned a wrong answer
+    pub fn is_failure(&self) -> bool {
+        self.status.is_failure()
+            || self
+                .validation
+                .as_ref()
+                .map(|v| v.status != ValidationStatus::Pass)
+                .unwrap_or(false)
+    }
+}
+
+/// Read a query name, or a query number as `q{N}`
+fn query_name<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameOrNumber {
+        Name(String),
+        Number(u64),
+    }
+    Ok(match NameOrNumber::deserialize(deserializer)? {
+        NameOrNumber::Name(name) => name,
+        NameOrNumber::Number(n) => format!("q{}", n),
+    })
+}
+
+/// Query times of results files written before queries had results of their own
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LegacyResults {
+    query_times: Vec<(u64, Vec<u128>)>,
+}
+
+impl Results {
+    pub fn new() -> Self {
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards");
+        Self {
+            system_time: current_time.as_millis(),
+            engine: String::new(),
+            engine_version: String::new(),
+            datafusion_version: String::new(),
+            datafusion_github_sha: None,
+            build: BTreeMap::new(),
+            cluster: BTreeMap::new(),
+            config: HashMap::new(),
+            command_line_args: std::env::args().collect(),
+            manifest: None,
+            register_tables_time: 0,
+            query_results: vec![],
+        }
+    }
+
+    /// Read a results json file written by [`Results::write`], or by the earlier runners that
+    /// recorded `query_times` as `[[query_number, [times...]], ...]`
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let mut results: Self = serde_json::from_str(&json)?;
+        if results.query_results.is_empty() {
+            let legacy: LegacyResults = serde_json::from_str(&json)?;
+            results.query_results = legacy
+                .query_times
+                .into_iter()
+                .map(|(query, times)| QueryResult::new(format!("q{}", query), vec![], times))
+                .collect();
+        }
+        Ok(results)
+    }
+
+    /// Write the results json file and a csv summary file. The second csv column is the median
+    /// time, followed by the remaining statistics and the query status.
+    pub fn write(&self, output_path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let f = File::create(format!("{}/results-{}.yaml", output_path, self.system_time))?;
+        let mut w = BufWriter::new(f);
+        w.write_all(json.as_bytes())?;
+
+        let mut w = File::create(format!("{}/results.csv", output_path))?;
+        w.write_all(
+            b"query,median_ms,min_ms,max_ms,mean_ms,p90_ms,stddev_ms,cv,ci95_lower_ms,ci95_upper_ms,iterations,status\n",
+        )?;
+        w.write_all(format!("setup,{},,,,,,,,,,\n", self.register_tables_time).as_bytes())?;
+        for result in &self.query_results {
+            let s = &result.statistics;
+            w.write_all(
+                format!(
+                    "{},{:.1},{:.1},{:.1},{:.1},{:.1},{:.1},{:.4},{:.1},{:.1},{},{}\n",
+                    result.query,
+                    s.median,
+                    s.min,
+                    s.max,
+                    s.mean,
+                    s.p90,
+                    s.stddev,
+                    s.cv,
+                    s.ci95_lower,
+                    s.ci95_upper,
+                    s.iterations,
+                    result.status
+                )
+                .as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,535 @@
+use crate::events::{Event, EventLog, EVENT_LOG};
+use crate::interrupt::Interrupt;
+use crate::isolation::{report, run_isolated, Isolation};
+use crate::manifest::Manifest;
+use crate::query::{
+    check_timeout_overrides, discover_queries, excluded_queries, is_query, read_query,
+    select_queries, Query,
+};
+use crate::table::discover_tables;
+use crate::validate::{compare, find_expected, read_expected, Validation};
+use crate::{
+    Engine, Error, ErrorCategory, Opt, PhaseTimes, QueryError, QueryResult, QueryStatus, Result,
+    ResultMode, Results, TimeoutError,
+};
+use futures::future::{select, Either};
+use futures::FutureExt;
+use std::any::Any;
+use std::fs::File;
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Run the benchmark described by `opt` against engine `E` and write the results files. Returns
+/// the number of queries that failed or returned a wrong answer.
+pub async fn run<E: Engine>(opt: &Opt) -> Result<usize> {
+    if opt.expected_path.is_some() && opt.result_mode != ResultMode::Collect {
+        return Err("--expected-path requires --result-mode collect".into());
+    }
+    check_timeout_overrides(opt, &discover_queries(opt)?)?;
+
+    let mut results = Results::new();
+    results.engine = opt.engine.clone();
+    results.datafusion_github_sha = opt.rev.clone();
+
+    let output_path = format!("{}", opt.output.display());
+
+    // listen for signals before the engine is set up, so that an isolated child process that is
+    // interrupted during setup reports its query as interrupted rather than being killed
+    let interrupt = Interrupt::listen();
+
+    // register all tables in data directory
+    let start = Instant::now();
+    let engine = E::try_new(opt).await?;
+    results.engine_version = engine.engine_version();
+    results.datafusion_version = engine.datafusion_version();
+    results.build = engine.build_info();
+    match engine.cluster_versions().await {
+        Ok(cluster) => results.cluster = cluster,
+        Err(e) => println!("Warning! Failed to get cluster versions: {}", e),
+    }
+    results.config = engine.config();
+    register_tables(&engine, opt, &mut results).await?;
+
+    let setup_time = start.elapsed().as_millis();
+    println!("Setup time was {} ms", setup_time);
+    results.register_tables_time = setup_time;
+
+    // an isolated child process runs a single query and reports the result to its parent
+    let log_path = opt.output.join(EVENT_LOG);
+    if let Some(name) = &opt.child_query {
+        let query = discover_queries(opt)?
+            .into_iter()
+            .find(|q| q.name == *name)
+            .ok_or_else(|| format!("Query {} not found", name))?;
+        let mut log = EventLog::open(&log_path)?;
+        let query_result = if interrupt.is_interrupted() {
+            QueryResult::incomplete(query.name.clone(), QueryStatus::Interrupted, None)
+        } else {
+            run_interruptible(&engine, opt, &query, &mut log, &interrupt).await
+        };
+        report(&query_result)?;
+        return Ok(0);
+    }
+
+    // when resuming into the same directory, append to its event log rather than copying the
+    // completed queries into a new one
+    let (completed, mut log, appending) = match &opt.resume {
+        Some(run_dir) => {
+            let previous_log = run_dir.join(EVENT_LOG);
+            let completed = EventLog::completed_queries(&previous_log)?;
+            println!(
+                "Resuming {} with {} completed queries",
+                run_dir.display(),
+                completed.len()
+            );
+            if same_file(&previous_log, &log_path) {
+                (completed, EventLog::open(&log_path)?, true)
+            } else {
+                (completed, EventLog::create(&log_path)?, false)
+            }
+        }
+        None => (vec![], EventLog::create(&log_path)?, false),
+    };
+    log.append(&Event::RunStarted {
+        results: results.clone(),
+    })?;
+    if !appending {
+        for query_result in &completed {
+            log.append(&Event::QueryFinished {
+                result: query_result.clone(),
+            })?;
+        }
+    }
+
+    let all_queries = discover_queries(opt)?;
+    let excluded = excluded_queries(opt, &all_queries)?;
+    for query in select_queries(opt, all_queries)? {
+        if completed.iter().any(|r| r.query == query.name) {
+            println!(
+                "Skipping query {}, which completed in the resumed run",
+                query.name
+            );
+            continue;
+        }
+        if interrupt.is_interrupted() {
+            // record the query the run stopped at, so that the run doesn't look complete
+            log.append(&Event::QueryFinished {
+                result: QueryResult::incomplete(query.name, QueryStatus::Interrupted, None),
+            })?;
+            break;
+        }
+
+        let query_result = if excluded.contains(&query.name) {
+            println!("Skipping query {}", query.name);
+            QueryResult::incomplete(query.name.clone(), QueryStatus::Skipped, None)
+        } else {
+            match opt.isolation {
+                Isolation::None => {
+                    run_interruptible(&engine, opt, &query, &mut log, &interrupt).await
+                }
+                _ => run_isolated(opt, &query.name, &interrupt).await?,
+            }
+        };
+        log.append(&Event::QueryFinished {
+            result: query_result,
+        })?;
+    }
+
+    // the results files are built from the event log, which includes the resumed queries
+    let results = EventLog::read_results(&log_path)?;
+    results.write(&output_path)?;
+
+    let failures = results
+        .query_results
+        .iter()
+        .filter(|r| r.is_failure())
+        .count();
+    if failures > 0 {
+        println!("{} queries failed", failures);
+    }
+    Ok(failures)
+}
+
+/// Whether two paths are the same file
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Run a query, cancelling it if the runner is interrupted
+async fn run_interruptible<E: Engine>(
+    engine: &E,
+    opt: &Opt,
+    query: &Query,
+    log: &mut EventLog,
+    interrupt: &Interrupt,
+) -> QueryResult {
+    let mut interrupt = interrupt.clone();
+    let running = Box::pin(run_query(engine, opt, query, log));
+    let interrupted = Box::pin(async move { interrupt.wait().await });
+    match select(running, interrupted).await {
+        Either::Left((query_result, _)) => query_result,
+        Either::Right((_, running)) => {
+            drop(running);
+            cancel_query(engine, query).await;
+            println!("Query {} interrupted", query.name);
+            QueryResult::incomplete(query.name.clone(), QueryStatus::Interrupted, None)
+        }
+    }
+}
+
+/// Cancel a query whose execution was dropped. Dropping the execution cancels it locally, but a
+/// cluster may need to be told.
+async fn cancel_query<E: Engine>(engine: &E, query: &Query) {
+    if let Err(e) = engine.cancel().await {
+        println!("Warning! Failed to cancel query {}: {}", query.name, e);
+    }
+}
+
+/// Run a query and record its outcome. A panic in the engine fails the query rather than the
+/// whole run.
+async fn run_query<E: Engine>(
+    engine: &E,
+    opt: &Opt,
+    query: &Query,
+    log: &mut EventLog,
+) -> QueryResult {
+    let result = AssertUnwindSafe(execute_query(engine, opt, query, log))
+        .catch_unwind()
+        .await;
+    let (status, error) = match result {
+        Ok(Ok(query_result)) => return query_result,
+        Ok(Err(e)) if e.is::<TimeoutError>() => (
+            QueryStatus::TimedOut,
+            QueryError::new(ErrorCategory::Execution, e.to_string()),
+        ),
+        Ok(Err(e)) => (
+            QueryStatus::Failed,
+            QueryError::from_error(e, ErrorCategory::Execution),
+        ),
+        Err(panic) => (
+            QueryStatus::Panicked,
+            QueryError::new(ErrorCategory::Execution, panic_message(panic)),
+        ),
+    };
+    println!("Query {} {}: {}", query.name, status, error);
+    QueryResult::incomplete(query.name.clone(), status, Some(error))
+}
+
+/// Message of a panic, which is usually a string
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Categorize an error from the engine, falling back to the category of the phase it occurred in
+fn query_error<E: Engine>(engine: &E, error: Error, category: ErrorCategory) -> Error {
+    let error = match engine.error_category(&error) {
+        Some(category) => QueryError::new(category, error.to_string()),
+        None => QueryError::from_error(error, category),
+    };
+    Box::new(error)
+}
+
+/// Register the tables declared in the manifest, or all tables in the data directory. Tables
+/// found in the data directory that the engine can't register are skipped with a warning.
+async fn register_tables<E: Engine>(engine: &E, opt: &Opt, results: &mut Results) -> Result<()> {
+    let (tables, declared) = match &opt.tables {
+        Some(path) => {
+            let manifest = Manifest::read(path)?;
+            let tables = manifest.tables(&opt.data_path)?;
+            results.manifest = Some(manifest);
+            (tables, true)
+        }
+        None => (discover_tables(opt)?, false),
+    };
+    for table in tables {
+        println!(
+            "Registering table {} as {}",
+            table.name,
+            table.paths.join(", ")
+        );
+        match engine.register_table(&table).await {
+            Ok(()) => {}
+            Err(e) if declared => {
+                return Err(format!("Failed to register table {}: {}", table.name, e).into())
+            }
+            Err(e) => println!("Warning! Skipping table {}: {}", table.name, e),
+        }
+    }
+    Ok(())
+}
+
+/// Execute the warmup and measured iterations of a query, or only the iteration given with
+/// `--child-iteration`. Plans and results are written to the output directory on the first
+/// iteration. An event is appended to the event log after each iteration.
+pub async fn execute_query<E: Engine>(
+    engine: &E,
+    opt: &Opt,
+    query: &Query,
+    log: &mut EventLog,
+) -> Result<QueryResult> {
+    let output_path = format!("{}", opt.output.display());
+    let sql = read_query(query)?;
+    let multipart = sql.len() > 1;
+
+    let warmup = opt.warmup as u32;
+    let mut warmup_durations = vec![];
+    let mut durations = vec![];
+    let timeout = query_timeout(opt, &query.name);
+    let mut phase_times = vec![];
+    let mut validation: Option<Validation> = None;
+    let mut metrics = vec![];
+    let mut rows: Option<usize> = None;
+    let mut bytes: Option<usize> = None;
+    let iterations = match opt.child_iteration {
+        Some(iteration) => iteration..iteration + 1,
+        None => 0..warmup + opt.iterations as u32,
+    };
+    for iteration in iterations.clone() {
+        // duration for executing all queries in the file
+        let mut total_duration_millis = 0;
+        let mut phases = PhaseTimes::default();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        for (i, sql) in sql.iter().enumerate() {
+            if opt.debug {
+                println!("Query {}: {}", query.name, sql);
+            }
+
+            let file_suffix = if multipart {
+                format!("_part_{}", i + 1)
+            } else {
+                "".to_owned()
+            };
+
+            let start = Instant::now();
+            let statement = execute_statement(engine, sql, opt.result_mode);
+            let (plan, output, statement_phases) = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(start);
+                    match tokio::time::timeout(remaining, statement).await {
+                        Ok(result) => result?,
+                        Err(_) => {
+                            cancel_query(engine, query).await;
+                            return Err(Box::new(TimeoutError(timeout.unwrap_or_default())));
+                        }
+                    }
+                }
+                None => statement.await?,
+            };
+            let duration = start.elapsed();
+            let summary = engine.output_summary(&output);
+            if opt.debug {
+                let physical_plan = match statement_phases.physical_plan {
+                    Some(time) => format!("{:.3} ms", time),
+                    None => "n/a".to_string(),
+                };
+                println!(
+                    "Query {}{} parse {:.3} ms, optimize {:.3} ms, physical plan {}",
+                    query.name,
+                    file_suffix,
+                    statement_phases.parse,
+                    statement_phases.optimize,
+                    physical_plan
+                );
+            }
+            phases.add(&statement_phases);
+            total_duration_millis += duration.as_millis();
+            if iteration < warmup {
+                println!(
+                    "Query {}{} warmup executed in: {:?}",
+                    query.name, file_suffix, duration
+                );
+            } else {
+                println!(
+                    "Query {}{} executed in: {:?}",
+                    query.name, file_suffix, duration
+                );
+            }
+
+            if iteration == warmup {
+                if let Some(query_metrics) = engine.metrics(&output)? {
+                    metrics.push(query_metrics);
+                }
+                if let Some(n) = summary.rows {
+                    rows = Some(rows.unwrap_or(0) + n);
+                }
+                if let Some(n) = summary.bytes {
+                    bytes = Some(bytes.unwrap_or(0) + n);
+                }
+            }
+
+            if iteration == 0 {
+                let filename = format!(
+                    "{}/{}{}_logical_plan.txt",
+                    output_path, query.name, file_suffix
+                );
+                let mut file = File::create(&filename)?;
+                write!(file, "{}", engine.explain(&plan)?)?;
+
+                // write QPML
+                if let Some(qpml) = engine.qpml(&plan)? {
+                    let filename = format!(
+                        "{}/{}{}_logical_plan.qpml",
+                        output_path, query.name, file_suffix
+                    );
+                    let mut file = File::create(&filename)?;
+                    write!(file, "{}", qpml)?;
+                }
+
+                // write the executed physical plan with metrics
+                if let Some(physical_plan) = engine.physical_plan(&output)? {
+                    let filename = format!(
+                        "{}/{}{}_physical_plan.txt",
+                        output_path, query.name, file_suffix
+                    );
+                    let mut file = File::create(&filename)?;
+                    write!(file, "{}", physical_plan)?;
+                }
+
+                // results are only available when they were collected
+                if opt.result_mode != ResultMode::Collect {
+                    continue;
+                }
+
+                // validate results against the expected answer
+                if let Some(expected_path) = &opt.expected_path {
+                    let name = format!("{}{}", query.name, file_suffix);
+                    let actual = engine.output_rows(&output)?;
+                    // answers of multi-statement queries such as TPC-H q15 are usually named
+                    // after the query, and belong to the statement that returns rows
+                    let path = find_expected(expected_path, &name).or_else(|| {
+                        if multipart && !actual.is_empty() {
+                            find_expected(expected_path, &query.name)
+                        } else {
+                            None
+                        }
+                    });
+                    if let Some(path) = path {
+                        let part_validation = match read_expected(&path) {
+                            Ok(expected) => compare(
+                                &actual,
+                                &expected,
+                                engine.is_ordered(&plan)?,
+                                opt.float_tolerance,
+                            ),
+                            Err(e) => Validation::fail(format!(
+                                "Failed to read expected answer {}: {}",
+                                path.display(),
+                                e
+                            )),
+                        };
+                        validation = Some(match validation {
+                            Some(v) => v.combine(part_validation),
+                            None => part_validation,
+                        });
+                    }
+                }
+
+                // write results to disk
+                let prefix = format!("{}/{}{}", output_path, query.name, file_suffix);
+                engine
+                    .write_output(output, &prefix, opt.output_parquet)
+                    .await?;
+            }
+        }
+        if iteration < warmup {
+            warmup_durations.push(total_duration_millis);
+        } else {
+            durations.push(total_duration_millis);
+            phase_times.push(phases.clone());
+        }
+        log.append(&Event::Iteration {
+            query: query.name.clone(),
+            iteration,
+            warmup: iteration < warmup,
+            time: total_duration_millis,
+            phase_times: phases,
+        })?;
+    }
+
+    let mut query_result = QueryResult::new(query.name.clone(), warmup_durations, durations);
+    query_result.phase_times = phase_times;
+    query_result.rows = rows;
+    query_result.bytes = bytes;
+    query_result.metrics = metrics;
+    if opt.expected_path.is_some() && iterations.contains(&0) {
+        let validation = validation.unwrap_or_else(|| {
+            Validation::fail(format!("No expected answer found for query {}", query.name))
+        });
+        match &validation.message {
+            Some(message) => println!(
+                "Query {} validation: {:?} ({})",
+                query.name, validation.status, message
+            ),
+            None => println!("Query {} validation: {:?}", query.name, validation.status),
+        }
+        query_result.validation = Some(validation);
+    }
+    Ok(query_result)
+}
+
+/// Plan and execute a single statement, timing each phase
+async fn execute_statement<E: Engine>(
+    engine: &E,
+    sql: &str,
+    mode: ResultMode,
+) -> Result<(E::Plan, E::Output, PhaseTimes)> {
+    let start = Instant::now();
+    let plan = engine
+        .plan(sql)
+        .await
+        .map_err(|e| query_error(engine, e, ErrorCategory::Plan))?;
+    let parsed = Instant::now();
+    let plan = engine
+        .optimize(plan)
+        .map_err(|e| query_error(engine, e, ErrorCategory::Plan))?;
+    let optimized = Instant::now();
+    let physical_plan = engine
+        .create_physical_plan(&plan)
+        .await
+        .map_err(|e| query_error(engine, e, ErrorCategory::Plan))?;
+    let planned = Instant::now();
+    let output = engine
+        .execute(&physical_plan, mode)
+        .await
+        .map_err(|e| query_error(engine, e, ErrorCategory::Execution))?;
+    let phases = PhaseTimes {
+        parse: millis(parsed - start),
+        optimize: millis(optimized - parsed),
+        physical_plan: engine
+            .creates_physical_plan()
+            .then(|| millis(planned - optimized)),
+        execute: millis(planned.elapsed()),
+        first_batch: engine
+            .output_summary(&output)
+            .time_to_first_batch
+            .map(millis),
+    };
+    Ok((plan, output, phases))
+}
+
+/// Timeout of each iteration of a query, if any
+fn query_timeout(opt: &Opt, name: &str) -> Option<Duration> {
+    opt.query_timeout_override
+        .iter()
+        .rev()
+        .find(|arg| is_query(&arg.query, name))
+        .map(|arg| arg.seconds)
+        .or(opt.query_timeout)
+        .map(Duration::from_secs_f64)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+/// Two-sided 95% critical values of Student's t distribution for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Summary statistics for the measured iterations of a query. All times are in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Statistics {
+    pub iterations: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    /// Sample standard deviation
+    pub stddev: f64,
+    /// Coefficient of variation (stddev / mean)
+    pub cv: f64,
+    /// Lower bound of the 95% confidence interval for the mean
+    pub ci95_lower: f64,
+    /// Upper bound of the 95% confidence interval for the mean
+    pub ci95_upper: f64,
+}
+
+impl Statistics {
+    pub fn from_times(times: &[u128]) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = times.iter().map(|t| *t as f64).collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let n = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let stddev = if n > 1 {
+            let variance = sorted.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+        let cv = if mean > 0.0 { stddev / mean } else { 0.0 };
+        let margin = if n > 1 {
+            t_critical_95(n - 1) * stddev / (n as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        Self {
+            iterations: n,
+            min: sorted[0],
+            max: sorted[n - 1],
+            mean,
+            median: percentile(&sorted, 0.5),
+            p90: percentile(&sorted, 0.9),
+            stddev,
+            cv,
+            ci95_lower: mean - margin,
+            ci95_upper: mean + margin,
+        }
+    }
+}
+
+/// Two-sided 95% critical value of Student's t distribution
+pub fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::NAN,
+        n if n <= T_95.len() => T_95[n - 1],
+        _ => 1.96,
+    }
+}
+
+/// Percentile of sorted values using linear interpolation between the closest ranks
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_times() {
+        assert_eq!(Statistics::from_times(&[]), Statistics::default());
+    }
+
+    #[test]
+    fn single_time() {
+        let stats = Statistics::from_times(&[42]);
+        assert_eq!(stats.iterations, 1);
+        assert_close(stats.median, 42.0);
+        assert_close(stats.p90, 42.0);
+        assert_close(stats.stddev, 0.0);
+        assert_close(stats.ci95_lower, 42.0);
+        assert_close(stats.ci95_upper, 42.0);
+    }
+
+    #[test]
+    fn unsorted_times() {
+        let stats = Statistics::from_times(&[30, 10, 40, 20]);
+        assert_eq!(stats.iterations, 4);
+        assert_close(stats.min, 10.0);
+        assert_close(stats.max, 40.0);
+        assert_close(stats.mean, 25.0);
+        assert_close(stats.median, 25.0);
+        assert_close(stats.p90, 37.0);
+        let stddev = (500.0_f64 / 3.0).sqrt();
+        assert_close(stats.stddev, stddev);
+        assert_close(stats.cv, stddev / 25.0);
+        assert_close(stats.ci95_lower, 25.0 - 3.182 * stddev / 2.0);
+        assert_close(stats.ci95_upper, 25.0 + 3.182 * stddev / 2.0);
+    }
+
+    #[test]
+    fn median_of_odd_count() {
+        assert_close(Statistics::from_times(&[5, 1, 3]).median, 3.0);
+    }
+
+    #[test]
+    fn t_critical_values() {
+        assert!(t_critical_95(0).is_nan());
+        assert_close(t_critical_95(1), 12.706);
+        assert_close(t_critical_95(30), 2.042);
+        assert_close(t_critical_95(31), 1.96);
+    }
+}
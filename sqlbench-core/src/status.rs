@@ -0,0 +1,121 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Outcome of running a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryStatus {
+    /// All iterations completed
+    #[default]
+    Ok,
+    /// The query returned an error
+    Failed,
+    /// The query was excluded with `--exclude`
+    Skipped,
+    /// The query did not complete within its timeout
+    TimedOut,
+    /// The engine panicked while running the query
+    Panicked,
+    /// The process running the query crashed, with `--isolation`
+    Crashed,
+    /// The run was interrupted while the query was running
+    Interrupted,
+}
+
+impl QueryStatus {
+    /// Whether the query ran into a problem. Skipped queries are not failures.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, QueryStatus::Ok | QueryStatus::Skipped)
+    }
+}
+
+impl fmt::Display for QueryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            QueryStatus::Ok => "ok",
+            QueryStatus::Failed => "failed",
+            QueryStatus::Skipped => "skipped",
+            QueryStatus::TimedOut => "timed_out",
+            QueryStatus::Panicked => "panicked",
+            QueryStatus::Crashed => "crashed",
+            QueryStatus::Interrupted => "interrupted",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Kind of error that made a query fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The SQL could not be parsed
+    SqlParse,
+    /// The query could not be planned, e.g. an unknown table or column or an unsupported feature
+    Plan,
+    /// The query failed during execution
+    Execution,
+    /// The query ran out of memory or another resource
+    ResourcesExhausted,
+    /// Reading or writing a file failed
+    Io,
+}
+
+/// Error from running a query, with its category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl QueryError {
+    pub fn new(category: ErrorCategory, message: String) -> Self {
+        Self { category, message }
+    }
+
+    /// Categorize an error that is not a [`QueryError`] already. IO errors are categorized as
+    /// such, and other errors get the given category.
+    pub fn from_error(error: Error, category: ErrorCategory) -> Self {
+        if let Some(e) = error.downcast_ref::<QueryError>() {
+            return e.clone();
+        }
+        let category = if error.is::<std::io::Error>() {
+            ErrorCategory::Io
+        } else {
+            category
+        };
+        Self::new(category, error.to_string())
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// How the child process running an isolated query crashed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Crash {
+    pub exit_code: Option<i32>,
+    /// Signal that killed the process, e.g. 9 when killed for running out of memory
+    pub signal: Option<i32>,
+    /// Last lines written to stderr
+    pub stderr_tail: Vec<String>,
+}
+
+/// Error returned when a query does not complete within its timeout
+#[derive(Debug)]
+pub struct TimeoutError(pub Duration);
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Query timed out after {:?}", self.0)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
@@ -0,0 +1,399 @@
+use crate::{Opt, Result};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A table to register with the engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub name: String,
+    /// Paths to files, or to directories of files
+    pub paths: Vec<String>,
+    pub format: FileFormat,
+    /// Arrow schema of the files. The schema is inferred from the files when not declared.
+    pub schema: Option<Vec<Column>>,
+    /// Hive-style partition columns as (name, Arrow data type) pairs, in directory order
+    pub partition_cols: Vec<(String, String)>,
+    /// Declared sort order of the files
+    pub sort_order: Vec<SortColumn>,
+}
+
+impl Table {
+    /// Table with a single path and no declared schema or sort order
+    fn new(
+        name: &str,
+        path: &Path,
+        format: FileFormat,
+        partition_cols: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            paths: vec![format!("{}", path.display())],
+            format,
+            schema: None,
+            partition_cols,
+            sort_order: vec![],
+        }
+    }
+}
+
+/// A column in a declared table schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// Arrow data type, e.g. `Int64` or `Decimal128(15, 2)`
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+/// A column in a declared sort order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortColumn {
+    pub name: String,
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl FromStr for SortColumn {
+    type Err = String;
+
+    /// Parse a sort column such as `l_orderkey`, `l_orderkey DESC` or `l_orderkey ASC NULLS FIRST`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let (name, modifiers) = words
+            .split_first()
+            .ok_or_else(|| "Empty sort column".to_string())?;
+        let modifiers = modifiers
+            .iter()
+            .map(|m| m.to_uppercase())
+            .collect::<Vec<_>>();
+        let modifiers = modifiers.iter().map(|m| m.as_str()).collect::<Vec<_>>();
+        let (descending, nulls) = match modifiers.as_slice() {
+            [] => (false, &[][..]),
+            ["ASC", nulls @ ..] => (false, nulls),
+            ["DESC", nulls @ ..] => (true, nulls),
+            nulls => (false, nulls),
+        };
+        // same defaults as SQL: NULLS LAST for ascending and NULLS FIRST for descending
+        let nulls_first = match nulls {
+            [] => descending,
+            ["NULLS", "FIRST"] => true,
+            ["NULLS", "LAST"] => false,
+            _ => return Err(format!("Invalid sort column: {}", s)),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            descending,
+            nulls_first,
+        })
+    }
+}
+
+/// A partition column declared on the command line as `table.column[:type]`, e.g.
+/// `lineitem.l_shipdate:Date32`. The type defaults to `Utf8`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionColumnArg {
+    pub table: String,
+    pub column: String,
+    pub data_type: String,
+}
+
+impl FromStr for PartitionColumnArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, data_type) = match s.split_once(':') {
+            Some((name, data_type)) => (name, data_type),
+            None => (s, "Utf8"),
+        };
+        match name.split_once('.') {
+            Some((table, column)) if !table.is_empty() && !column.is_empty() => Ok(Self {
+                table: table.to_string(),
+                column: column.to_string(),
+                data_type: data_type.to_string(),
+            }),
+            _ => Err(format!(
+                "Partition column must be declared as table.column[:type]: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// File format of a table, with format-specific options
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileFormat {
+    Parquet,
+    /// Delimited text files, such as `.csv` files or pipe-delimited TPC-H `.tbl` files
+    Csv(CsvOptions),
+    /// Newline-delimited JSON
+    Json(Compression),
+    Avro,
+    /// Arrow IPC files
+    Arrow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub has_header: bool,
+    pub compression: Compression,
+    /// File extension, e.g. `.csv` or `.tbl`, not including the compression extension
+    pub file_extension: String,
+    /// Each line ends with a delimiter, as in dbgen output. The empty field after it is ignored.
+    pub trailing_delimiter: bool,
+}
+
+/// Compression of text files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// File extension of compressed files, e.g. `.gz`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Uncompressed => "",
+            Compression::Gzip => ".gz",
+            Compression::Bzip2 => ".bz2",
+            Compression::Xz => ".xz",
+            Compression::Zstd => ".zst",
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "" | "none" | "uncompressed" => Ok(Compression::Uncompressed),
+            "gz" | "gzip" => Ok(Compression::Gzip),
+            "bz2" | "bzip2" => Ok(Compression::Bzip2),
+            "xz" => Ok(Compression::Xz),
+            "zst" | "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("Unsupported compression: {}", s)),
+        }
+    }
+}
+
+impl FileFormat {
+    /// Full file suffix of files in this format, including any compression extension
+    pub fn file_suffix(&self) -> String {
+        match self {
+            FileFormat::Parquet => ".parquet".to_string(),
+            FileFormat::Csv(options) => {
+                format!(
+                    "{}{}",
+                    options.file_extension,
+                    options.compression.extension()
+                )
+            }
+            FileFormat::Json(compression) => format!(".json{}", compression.extension()),
+            FileFormat::Avro => ".avro".to_string(),
+            FileFormat::Arrow => ".arrow".to_string(),
+        }
+    }
+}
+
+/// Parse a single-character delimiter command line argument
+pub fn parse_delimiter(s: &str) -> std::result::Result<u8, String> {
+    match s.as_bytes() {
+        [b] => Ok(*b),
+        _ if s == "\\t" => Ok(b'\t'),
+        _ => Err(format!("Delimiter must be a single character: {}", s)),
+    }
+}
+
+/// File formats of tables in the data directory, with options from the command line
+fn file_formats(opt: &Opt) -> Vec<FileFormat> {
+    vec![
+        FileFormat::Parquet,
+        FileFormat::Csv(CsvOptions {
+            delimiter: opt.csv_delimiter,
+            has_header: !opt.csv_no_header,
+            compression: opt.csv_compression,
+            file_extension: ".csv".to_string(),
+            trailing_delimiter: false,
+        }),
+        FileFormat::Csv(CsvOptions {
+            delimiter: opt.tbl_delimiter,
+            has_header: false,
+            compression: opt.tbl_compression,
+            file_extension: ".tbl".to_string(),
+            trailing_delimiter: true,
+        }),
+        FileFormat::Json(opt.json_compression),
+        FileFormat::Avro,
+        FileFormat::Arrow,
+    ]
+}
+
+/// Find the tables in the data directory. Each file with a known format is a table named after
+/// the file, e.g. `lineitem.parquet` or `lineitem.tbl` is registered as `lineitem`. Each
+/// subdirectory is a table named after the directory, in the format of the first file found in
+/// it. Hive-style partition columns (`l_shipdate=.../`) are inferred as `Utf8` columns unless
+/// declared on the command line.
+pub fn discover_tables(opt: &Opt) -> Result<Vec<Table>> {
+    let formats = file_formats(opt);
+    let mut tables = vec![];
+    for file in fs::read_dir(&opt.data_path)? {
+        let file_path = file?.path();
+        let filename = match Path::file_name(&file_path).and_then(|f| f.to_str()) {
+            Some(filename) if !is_hidden(filename) => filename,
+            _ => continue,
+        };
+        if file_path.is_dir() {
+            match find_format(&file_path, &formats)? {
+                Some(format) => {
+                    let partition_cols = partition_cols(opt, filename, &file_path)?;
+                    tables.push(Table::new(filename, &file_path, format, partition_cols));
+                }
+                None => println!(
+                    "Warning! Skipping directory {} with no supported files",
+                    file_path.display()
+                ),
+            }
+            continue;
+        }
+        if let Some(format) = formats
+            .iter()
+            .find(|format| filename.ends_with(&format.file_suffix()))
+        {
+            let suffix = format.file_suffix();
+            let name = &filename[0..filename.len() - suffix.len()];
+            tables.push(Table::new(name, &file_path, format.clone(), vec![]));
+        }
+    }
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tables)
+}
+
+/// Files and directories such as `_SUCCESS` or `.crc` files are not data
+fn is_hidden(filename: &str) -> bool {
+    filename.starts_with('.') || filename.starts_with('_')
+}
+
+/// Format of the first data file found in a directory or its subdirectories
+fn find_format(dir: &Path, formats: &[FileFormat]) -> Result<Option<FileFormat>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        let filename = match Path::file_name(&path).and_then(|f| f.to_str()) {
+            Some(filename) if !is_hidden(filename) => filename,
+            _ => continue,
+        };
+        if path.is_dir() {
+            if let Some(format) = find_format(&path, formats)? {
+                return Ok(Some(format));
+            }
+        } else if let Some(format) = formats
+            .iter()
+            .find(|format| filename.ends_with(&format.file_suffix()))
+        {
+            return Ok(Some(format.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Partition columns of a table, either declared on the command line or inferred from the
+/// `key=value` directory names under the table directory
+fn partition_cols(opt: &Opt, table_name: &str, dir: &Path) -> Result<Vec<(String, String)>> {
+    let declared = opt
+        .partition_col
+        .iter()
+        .filter(|col| col.table == table_name)
+        .map(|col| (col.column.clone(), col.data_type.clone()))
+        .collect::<Vec<_>>();
+    if !declared.is_empty() {
+        return Ok(declared);
+    }
+
+    let mut cols = vec![];
+    let mut dir = dir.to_path_buf();
+    loop {
+        let mut subdirs = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        subdirs.retain(|path| path.is_dir());
+        subdirs.sort();
+        let partition = subdirs.into_iter().find_map(|path| {
+            let filename = Path::file_name(&path)?.to_str()?.to_string();
+            let (key, _) = filename.split_once('=')?;
+            Some((key.to_string(), path.clone()))
+        });
+        match partition {
+            Some((key, path)) => {
+                cols.push((key, "Utf8".to_string()));
+                dir = path;
+            }
+            None => return Ok(cols),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_columns() {
+        for (s, descending, nulls_first) in [
+            ("l_orderkey", false, false),
+            ("l_orderkey ASC", false, false),
+            ("l_orderkey DESC", true, true),
+            ("l_orderkey asc nulls first", false, true),
+            ("l_orderkey DESC NULLS LAST", true, false),
+            ("l_orderkey NULLS FIRST", false, true),
+        ] {
+            let expected = SortColumn {
+                name: "l_orderkey".to_string(),
+                descending,
+                nulls_first,
+            };
+            assert_eq!(s.parse::<SortColumn>(), Ok(expected), "{}", s);
+        }
+        for s in [
+            "",
+            "l_orderkey DESC ASC",
+            "l_orderkey NULLS",
+            "l_orderkey FIRST",
+        ] {
+            assert!(s.parse::<SortColumn>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn partition_column_args() {
+        let arg = "lineitem.l_shipdate:Date32".parse::<PartitionColumnArg>();
+        assert_eq!(
+            arg,
+            Ok(PartitionColumnArg {
+                table: "lineitem".to_string(),
+                column: "l_shipdate".to_string(),
+                data_type: "Date32".to_string(),
+            })
+        );
+        let arg = "lineitem.l_shipdate".parse::<PartitionColumnArg>().unwrap();
+        assert_eq!(arg.data_type, "Utf8");
+        for s in ["l_shipdate", "lineitem.", ".l_shipdate:Date32"] {
+            assert!(s.parse::<PartitionColumnArg>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn delimiters() {
+        assert_eq!(parse_delimiter("|"), Ok(b'|'));
+        assert_eq!(parse_delimiter("\\t"), Ok(b'\t'));
+        assert!(parse_delimiter("").is_err());
+        assert!(parse_delimiter("||").is_err());
+    }
+}
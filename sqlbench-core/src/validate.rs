@@ -0,0 +1,345 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Values that are treated as NULL when comparing answers
+const NULL_VALUES: [&str; 4] = ["", "NULL", "null", "\\N"];
+
+/// Outcome of validating a query against its expected answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ValidationStatus {
+    /// The query returned the expected answer
+    Pass,
+    /// The query returned a different answer
+    Mismatch,
+    /// The answer could not be validated, for example because there is no expected answer
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validation {
+    pub status: ValidationStatus,
+    pub message: Option<String>,
+}
+
+impl Validation {
+    fn new(status: ValidationStatus, message: Option<String>) -> Self {
+        Self { status, message }
+    }
+
+    pub fn pass() -> Self {
+        Self::new(ValidationStatus::Pass, None)
+    }
+
+    pub fn mismatch(message: String) -> Self {
+        Self::new(ValidationStatus::Mismatch, Some(message))
+    }
+
+    pub fn fail(message: String) -> Self {
+        Self::new(ValidationStatus::Fail, Some(message))
+    }
+
+    /// Combine the validations of the statements in a multi-statement query, keeping the worst
+    pub fn combine(self, other: Self) -> Self {
+        if other.status > self.status {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Find the expected answer for a query output named e.g. `q1` or `q15_part_2`. Answers can be
+/// CSV files with a header (`q1.csv` as written by this runner, or a directory of CSV part files
+/// as written by earlier versions of the DataFusion runner) or pipe-delimited files with a header
+/// (`q1.out`, `q1.tbl`) such as the official TPC-H answers.
+pub fn find_expected(expected_path: &Path, name: &str) -> Option<PathBuf> {
+    ["csv", "out", "tbl"]
+        .iter()
+        .map(|ext| expected_path.join(format!("{}.{}", name, ext)))
+        .find(|path| path.exists())
+}
+
+/// Read an expected answer, skipping the header row
+pub fn read_expected(path: &Path) -> Result<Vec<Vec<String>>> {
+    let delimiter = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("out") | Some("tbl") => b'|',
+        _ => b',',
+    };
+    let files = if path.is_dir() {
+        let mut files = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        files.retain(|f| f.is_file());
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut rows = vec![];
+    for file in files {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(true)
+            .flexible(true)
+            .from_path(&file)?;
+        for record in reader.records() {
+            let mut row = record?.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            // dbgen style files end each line with a delimiter
+            if delimiter == b'|' && row.last().map(|v| v.is_empty()).unwrap_or(false) {
+                row.pop();
+            }
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+/// Compare query output with the expected answer. Row order is ignored unless `ordered` is
+/// true. Numeric values match when they are within `tolerance` of each other, so `12.50`
+/// matches `12.5`, and all representations of NULL match each other.
+pub fn compare(
+    actual: &[Vec<String>],
+    expected: &[Vec<String>],
+    ordered: bool,
+    tolerance: f64,
+) -> Validation {
+    if actual.len() != expected.len() {
+        return Validation::mismatch(format!(
+            "Expected {} rows but got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+
+    let mut actual = actual.iter().map(|r| normalize(r)).collect::<Vec<_>>();
+    let mut expected = expected.iter().map(|r| normalize(r)).collect::<Vec<_>>();
+    if !ordered {
+        actual.sort_by(|a, b| compare_rows(a, b));
+        expected.sort_by(|a, b| compare_rows(a, b));
+    }
+
+    for (i, (a, e)) in actual.iter().zip(expected.iter()).enumerate() {
+        if a.len() != e.len() {
+            return Validation::mismatch(format!(
+                "Expected {} columns but got {} in row {}",
+                e.len(),
+                a.len(),
+                i + 1
+            ));
+        }
+        for (j, (av, ev)) in a.iter().zip(e.iter()).enumerate() {
+            if !values_match(av, ev, tolerance) {
+                return Validation::mismatch(format!(
+                    "Row {} column {}: expected {} but got {}",
+                    i + 1,
+                    j + 1,
+                    ev,
+                    av
+                ));
+            }
+        }
+    }
+    Validation::pass()
+}
+
+/// A normalized value from an answer
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(s) => write!(f, "'{}'", s),
+        }
+    }
+}
+
+fn normalize(row: &[String]) -> Vec<Value> {
+    row.iter()
+        .map(|v| {
+            let v = v.trim();
+            if NULL_VALUES.contains(&v) {
+                Value::Null
+            } else if let Ok(n) = v.parse::<f64>() {
+                Value::Number(n)
+            } else {
+                Value::Text(v.to_string())
+            }
+        })
+        .collect()
+}
+
+fn values_match(actual: &Value, expected: &Value, tolerance: f64) -> bool {
+    match (actual, expected) {
+        (Value::Number(a), Value::Number(e)) => (a - e).abs() <= tolerance,
+        _ => actual == expected,
+    }
+}
+
+fn compare_rows(a: &[Value], b: &[Value]) -> Ordering {
+    for (av, bv) in a.iter().zip(b.iter()) {
+        let ordering = match (av, bv) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Number(x), Value::Number(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+            (Value::Number(_), Value::Text(_)) => Ordering::Less,
+            (Value::Text(_), Value::Number(_)) => Ordering::Greater,
+            (Value::Text(x), Value::Text(y)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    fn status(actual: &[&[&str]], expected: &[&[&str]], ordered: bool) -> ValidationStatus {
+        compare(&rows(actual), &rows(expected), ordered, 0.01).status
+    }
+
+    /// Write a file to a temporary directory and return the directory
+    fn write_answer(dir: &str, name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sqlbench-{}-{}", std::process::id(), dir));
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        dir
+    }
+
+    #[test]
+    fn decimal_representations_match() {
+        let pass = ValidationStatus::Pass;
+        assert_eq!(status(&[&["12.50"]], &[&["12.5"]], false), pass);
+        assert_eq!(status(&[&["1e2"]], &[&["100.00"]], false), pass);
+        assert_eq!(status(&[&[" 7 "]], &[&["7"]], false), pass);
+    }
+
+    #[test]
+    fn numbers_within_tolerance() {
+        assert_eq!(
+            status(&[&["0.5"]], &[&["0.505"]], false),
+            ValidationStatus::Pass
+        );
+        assert_eq!(
+            status(&[&["0.5"]], &[&["0.515"]], false),
+            ValidationStatus::Mismatch
+        );
+        assert_eq!(
+            status(&[&["1000"]], &[&["999.985"]], false),
+            ValidationStatus::Mismatch
+        );
+    }
+
+    #[test]
+    fn nulls_match_empty_values() {
+        // the CSV output of the runner writes NULL as an empty value
+        assert_eq!(
+            status(&[&[""]], &[&["NULL"]], false),
+            ValidationStatus::Pass
+        );
+        assert_eq!(
+            status(&[&["\\N"]], &[&["null"]], false),
+            ValidationStatus::Pass
+        );
+        assert_eq!(
+            status(&[&[""]], &[&["0"]], false),
+            ValidationStatus::Mismatch
+        );
+        assert_eq!(
+            status(&[&["NULL"]], &[&["none"]], false),
+            ValidationStatus::Mismatch
+        );
+    }
+
+    #[test]
+    fn text_must_match_exactly() {
+        assert_eq!(
+            status(&[&["ABC"]], &[&["abc"]], false),
+            ValidationStatus::Mismatch
+        );
+    }
+
+    #[test]
+    fn row_order_matters_only_when_ordered() {
+        let actual: &[&[&str]] = &[&["2", "b"], &["1", "a"]];
+        let expected: &[&[&str]] = &[&["1", "a"], &["2", "b"]];
+        assert_eq!(status(actual, expected, false), ValidationStatus::Pass);
+        assert_eq!(status(actual, expected, true), ValidationStatus::Mismatch);
+    }
+
+    #[test]
+    fn different_shapes_mismatch() {
+        assert_eq!(
+            status(&[&["1"]], &[&["1"], &["2"]], false),
+            ValidationStatus::Mismatch
+        );
+        assert_eq!(
+            status(&[&["1", "2"]], &[&["1"]], false),
+            ValidationStatus::Mismatch
+        );
+    }
+
+    #[test]
+    fn read_pipe_delimited_answer() {
+        let contents = "l_returnflag|l_linestatus|sum_qty|\nA|F|37734107.00|\nN|O|991417.00|\n";
+        let dir = write_answer("out", "q1.out", contents);
+        let path = find_expected(&dir, "q1").unwrap();
+        let expected = read_expected(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            expected,
+            rows(&[&["A", "F", "37734107.00"], &["N", "O", "991417.00"]])
+        );
+    }
+
+    #[test]
+    fn read_pipe_delimited_answer_without_trailing_delimiter() {
+        let dir = write_answer("tbl", "q2.tbl", "a|b\n1|\n");
+        let expected = read_expected(&dir.join("q2.tbl")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // only an empty value after the last delimiter is dropped
+        assert_eq!(expected, rows(&[&["1"]]));
+    }
+
+    #[test]
+    fn read_csv_answer() {
+        let dir = write_answer("csv", "q3.csv", "a,b\n1,\"x,y\"\n");
+        let path = find_expected(&dir, "q3").unwrap();
+        let expected = read_expected(&path).unwrap();
+        assert!(find_expected(&dir, "q4").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(expected, rows(&[&["1", "x,y"]]));
+    }
+
+    #[test]
+    fn read_csv_part_files() {
+        let dir = write_answer("parts", "q5.csv/part-1.csv", "a\n2\n");
+        let parts = dir.join("q5.csv");
+        fs::write(parts.join("part-0.csv"), "a\n1\n").unwrap();
+        let expected = read_expected(&parts).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(expected, rows(&[&["1"], &["2"]]));
+    }
+}
@@ -0,0 +1,66 @@
+//! Records the versions and sources of the engine crates from Cargo.lock, and the git commit of
+//! the runner, so that results files can be traced back to the build that produced them.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let lock_path = Path::new(&manifest_dir).join("../Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let mut dependencies = vec![];
+    if let Ok(lock) = fs::read_to_string(&lock_path) {
+        for package in lock.split("[[package]]") {
+            let field = |name: &str| {
+                let prefix = format!("{} = \"", name);
+                package.lines().find_map(|line| {
+                    line.strip_prefix(&prefix)?
+                        .strip_suffix('"')
+                        .map(|s| s.to_string())
+                })
+            };
+            let (name, version) = match (field("name"), field("version")) {
+                (Some(name), Some(version)) => (name, version),
+                _ => continue,
+            };
+            if !name.starts_with("datafusion") && !name.starts_with("ballista") {
+                continue;
+            }
+            // git sources record the rev from Cargo.toml and the resolved commit
+            match field("source").filter(|source| source.starts_with("git+")) {
+                Some(source) => dependencies.push(format!("{}={} ({})", name, version, source)),
+                None => dependencies.push(format!("{}={}", name, version)),
+            }
+        }
+    }
+    println!(
+        "cargo:rustc-env=SQLBENCH_DEPENDENCIES={}",
+        dependencies.join(";")
+    );
+
+    // rerun when HEAD moves to another branch or the branch moves to another commit
+    let mut git_files = vec!["HEAD".to_string(), "packed-refs".to_string()];
+    git_files.extend(git(&["symbolic-ref", "-q", "HEAD"]));
+    for file in git_files {
+        if let Some(path) = git(&["rev-parse", "--git-path", &file]) {
+            if Path::new(&path).exists() {
+                println!("cargo:rerun-if-changed={}", path);
+            }
+        }
+    }
+
+    let git_sha = git(&["rev-parse", "HEAD"]).unwrap_or_default();
+    println!("cargo:rustc-env=SQLBENCH_GIT_SHA={}", git_sha);
+}
+
+/// Output of a git command, if it succeeded
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,508 @@
+#[cfg(feature = "qpml")]
+use crate::util::qpml;
+use crate::util::{
+    batches_to_rows, build_info, error_category, is_ordered, read_stream, table_provider,
+    write_batches, ResultSet,
+};
+use async_trait::async_trait;
+use ballista::prelude::*;
+use ballista_core::client::BallistaClient;
+use ballista_core::config::{
+    BALLISTA_DEFAULT_BATCH_SIZE, BALLISTA_PARQUET_PRUNING, BALLISTA_REPARTITION_AGGREGATIONS,
+    BALLISTA_REPARTITION_JOINS, BALLISTA_REPARTITION_WINDOWS, BALLISTA_WITH_INFORMATION_SCHEMA,
+};
+use ballista_core::serde::protobuf::execute_query_params::{OptionalSessionId, Query};
+use ballista_core::serde::protobuf::scheduler_grpc_client::SchedulerGrpcClient;
+use ballista_core::serde::protobuf::{
+    job_status, CancelJobParams, ExecuteQueryParams, GetJobStatusParams, KeyValuePair,
+    PartitionLocation,
+};
+use ballista_core::serde::{BallistaCodec, BallistaLogicalExtensionCodec};
+use ballista_core::BALLISTA_VERSION;
+use ballista_executor::new_standalone_executor;
+use ballista_scheduler::standalone::new_standalone_scheduler;
+use datafusion::arrow::datatypes::Schema;
+use datafusion::config::ConfigOptions;
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::prelude::{DataFrame, SessionConfig, SessionContext};
+use datafusion::scalar::ScalarValue;
+use datafusion::DATAFUSION_VERSION;
+use datafusion_proto::logical_plan::AsLogicalPlan;
+use datafusion_proto::protobuf::{LogicalPlanNode, PhysicalPlanNode};
+use futures::{StreamExt, TryStreamExt};
+use sqlbench_core::{
+    read_config_file, Engine, Error, ErrorCategory, FileFormat, Opt, OutputSummary, Result,
+    ResultMode, Table,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+
+pub struct BallistaEngine {
+    /// Client session that plans and optimizes queries, with the DataFusion config applied
+    ctx: SessionContext,
+    /// Ballista config, passed to the scheduler with each job
+    ballista_config: BallistaConfig,
+    /// Session on the scheduler that the jobs are submitted to
+    session_id: String,
+    /// Effective Ballista and DataFusion config
+    config: HashMap<String, String>,
+    scheduler: SchedulerGrpcClient<Channel>,
+    /// URL of the remote scheduler, or None for the in-process cluster
+    scheduler_url: Option<String>,
+    /// Number of in-process executors
+    executors: usize,
+    /// Job of the running query, to cancel when its execution is dropped
+    job_id: Mutex<Option<String>>,
+}
+
+/// A query planned by the Ballista client
+pub struct BallistaPlan {
+    df: DataFrame,
+    /// Logical plan of the query, which is optimized on the client before it is submitted
+    plan: LogicalPlan,
+}
+
+/// An optimized logical plan to submit as a job. The scheduler creates the physical plan.
+pub struct JobPlan {
+    plan: LogicalPlan,
+}
+
+/// Start an in-process scheduler and executors, returning the scheduler port
+async fn start_standalone_cluster(executors: usize, task_slots: usize) -> Result<u16> {
+    let addr = new_standalone_scheduler().await?;
+    let scheduler_url = format!("http://localhost:{}", addr.port());
+    println!(
+        "Started in-process scheduler on port {} with {} executors of {} task slots",
+        addr.port(),
+        executors,
+        task_slots
+    );
+    for _ in 0..executors {
+        let scheduler = connect_scheduler(&scheduler_url).await?;
+        let codec: BallistaCodec<LogicalPlanNode, PhysicalPlanNode> = BallistaCodec::default();
+        new_standalone_executor(scheduler, task_slots, codec).await?;
+    }
+    Ok(addr.port())
+}
+
+/// Connect to the scheduler, waiting for an in-process scheduler to start
+async fn connect_scheduler(scheduler_url: &str) -> Result<SchedulerGrpcClient<Channel>> {
+    let mut attempts = 0;
+    loop {
+        match SchedulerGrpcClient::connect(scheduler_url.to_string()).await {
+            Ok(scheduler) => return Ok(scheduler),
+            Err(e) if attempts >= 50 => {
+                return Err(
+                    format!("Failed to connect to scheduler at {}: {}", scheduler_url, e).into(),
+                )
+            }
+            Err(_) => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+/// Config of the client session, as BallistaContext creates it, with the DataFusion keys from the
+/// config file applied. Their values are validated by `try_new`.
+fn client_session_config(config: &BallistaConfig) -> SessionConfig {
+    let mut session_config = SessionConfig::new()
+        .with_target_partitions(config.default_shuffle_partitions())
+        .with_information_schema(config.default_with_information_schema());
+    for (key, value) in config.settings() {
+        if key.starts_with("datafusion.") {
+            session_config = session_config.set(key, ScalarValue::Utf8(Some(value.clone())));
+        }
+    }
+    session_config
+}
+
+/// Ballista settings as passed to the scheduler
+fn settings(config: &BallistaConfig) -> Vec<KeyValuePair> {
+    config
+        .settings()
+        .iter()
+        .map(|(key, value)| KeyValuePair {
+            key: key.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+/// Create a session on the scheduler for the jobs of the runner, as BallistaContext does
+async fn create_session(
+    scheduler: &mut SchedulerGrpcClient<Channel>,
+    config: &BallistaConfig,
+) -> Result<String> {
+    let params = ExecuteQueryParams {
+        query: None,
+        settings: settings(config),
+        optional_session_id: None,
+    };
+    Ok(scheduler
+        .execute_query(params)
+        .await?
+        .into_inner()
+        .session_id)
+}
+
+/// Effective config: the Ballista settings, and the config of the client session that plans and
+/// optimizes the queries
+fn effective_config(
+    config: &BallistaConfig,
+    session_config: &SessionConfig,
+) -> HashMap<String, String> {
+    let mut effective = HashMap::new();
+    for entry in session_config.config_options().entries() {
+        if let Some(value) = entry.value {
+            effective.insert(entry.key, value);
+        }
+    }
+    let ballista = [
+        (
+            BALLISTA_DEFAULT_SHUFFLE_PARTITIONS,
+            config.default_shuffle_partitions().to_string(),
+        ),
+        (
+            BALLISTA_DEFAULT_BATCH_SIZE,
+            config.default_batch_size().to_string(),
+        ),
+        (
+            BALLISTA_REPARTITION_JOINS,
+            config.repartition_joins().to_string(),
+        ),
+        (
+            BALLISTA_REPARTITION_AGGREGATIONS,
+            config.repartition_aggregations().to_string(),
+        ),
+        (
+            BALLISTA_REPARTITION_WINDOWS,
+            config.repartition_windows().to_string(),
+        ),
+        (
+            BALLISTA_PARQUET_PRUNING,
+            config.parquet_pruning().to_string(),
+        ),
+        (
+            BALLISTA_WITH_INFORMATION_SCHEMA,
+            config.default_with_information_schema().to_string(),
+        ),
+    ];
+    for (key, value) in ballista {
+        effective.insert(key.to_string(), value);
+    }
+    for (key, value) in config.settings() {
+        effective.insert(key.clone(), value.clone());
+    }
+    effective
+}
+
+impl BallistaEngine {
+    /// Submit an optimized logical plan to the scheduler as a job, the same way the Ballista
+    /// client does, and stream its output partitions from the executors once it completes. The
+    /// job id is recorded so that only this job is cancelled.
+    async fn execute_job(&self, plan: &LogicalPlan) -> Result<SendableRecordBatchStream> {
+        let mut buf = vec![];
+        let codec = BallistaLogicalExtensionCodec::default();
+        LogicalPlanNode::try_from_logical_plan(plan, &codec)?.try_encode(&mut buf)?;
+        let params = ExecuteQueryParams {
+            query: Some(Query::LogicalPlan(buf)),
+            settings: settings(&self.ballista_config),
+            optional_session_id: Some(OptionalSessionId::SessionId(self.session_id.clone())),
+        };
+
+        let mut scheduler = self.scheduler.clone();
+        let job_id = scheduler.execute_query(params).await?.into_inner().job_id;
+        *self.job_id.lock().unwrap() = Some(job_id.clone());
+        let locations = loop {
+            let status = scheduler
+                .get_job_status(GetJobStatusParams {
+                    job_id: job_id.clone(),
+                })
+                .await?
+                .into_inner()
+                .status
+                .and_then(|status| status.status);
+            match status {
+                Some(job_status::Status::Queued(_)) | Some(job_status::Status::Running(_)) => {
+                    tokio::time::sleep(Duration::from_millis(100)).await
+                }
+                Some(job_status::Status::Successful(job)) => break job.partition_location,
+                Some(job_status::Status::Failed(job)) => {
+                    *self.job_id.lock().unwrap() = None;
+                    return Err(format!("Job {} failed: {}", job_id, job.error).into());
+                }
+                None => return Err(format!("Received empty status of job {}", job_id).into()),
+            }
+        };
+        *self.job_id.lock().unwrap() = None;
+
+        let schema: Schema = plan.schema().as_ref().clone().into();
+        let batches = futures::stream::iter(locations)
+            .then(fetch_partition)
+            .try_flatten();
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            Arc::new(schema),
+            batches,
+        )))
+    }
+}
+
+/// Read an output partition of a job from the executor that holds it
+async fn fetch_partition(
+    location: PartitionLocation,
+) -> datafusion::error::Result<SendableRecordBatchStream> {
+    let executor = location
+        .executor_meta
+        .ok_or_else(|| DataFusionError::Internal("Received empty executor metadata".to_string()))?;
+    let partition_id = location
+        .partition_id
+        .ok_or_else(|| DataFusionError::Internal("Received empty partition id".to_string()))?;
+    let port = executor.port as u16;
+    let mut client = BallistaClient::try_new(&executor.host, port)
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+    client
+        .fetch_partition(
+            &executor.id,
+            &partition_id.into(),
+            &location.path,
+            &executor.host,
+            port,
+        )
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))
+}
+
+#[async_trait]
+impl Engine for BallistaEngine {
+    type Plan = BallistaPlan;
+    type PhysicalPlan = JobPlan;
+    type Output = ResultSet;
+
+    async fn try_new(opt: &Opt) -> Result<Self> {
+        let mut builder = BallistaConfig::builder().set(
+            BALLISTA_DEFAULT_SHUFFLE_PARTITIONS,
+            &format!("{}", opt.concurrency),
+        );
+
+        // Ballista keys configure the client and scheduler. DataFusion keys are passed through
+        // to the session on the scheduler.
+        if let Some(config_path) = &opt.config_path {
+            let ballista_keys = BallistaConfig::valid_entries();
+            let mut datafusion_options = ConfigOptions::new();
+            let datafusion_keys = datafusion_options
+                .entries()
+                .into_iter()
+                .map(|entry| entry.key)
+                .collect::<HashSet<_>>();
+            for (key, value) in read_config_file(config_path)? {
+                if !ballista_keys.contains_key(&key) && !datafusion_keys.contains(&key) {
+                    return Err(format!(
+                        "Unknown config key {} in {}. Expected a Ballista (ballista.*) or \
+                         DataFusion (datafusion.*) config key.",
+                        key,
+                        config_path.display()
+                    )
+                    .into());
+                }
+                // Ballista only checks the values of its own keys, and SessionConfig::set
+                // panics on invalid values
+                if datafusion_keys.contains(&key) {
+                    datafusion_options.set(&key, &value).map_err(|e| {
+                        format!(
+                            "Invalid config {}={} in {}: {}",
+                            key,
+                            value,
+                            config_path.display(),
+                            e
+                        )
+                    })?;
+                }
+                builder = builder.set(&key, &value);
+            }
+        }
+        let config = builder.build()?;
+
+        let (mut scheduler, scheduler_url) = match opt.engine.as_str() {
+            "ballista-standalone" => {
+                let task_slots = opt.task_slots.unwrap_or(opt.concurrency as usize);
+                let port = start_standalone_cluster(opt.executors, task_slots).await?;
+                let scheduler = connect_scheduler(&format!("http://localhost:{}", port)).await?;
+                (scheduler, None)
+            }
+            _ => {
+                let url = format!("http://{}:{}", opt.scheduler_host, opt.scheduler_port);
+                let scheduler = connect_scheduler(&url).await?;
+                (scheduler, Some(url))
+            }
+        };
+
+        // queries are planned and optimized in a client session, and submitted as jobs to a
+        // session on the scheduler, as BallistaContext does. The client session is created here
+        // so that the DataFusion config applies where the optimizer runs.
+        let session_id = create_session(&mut scheduler, &config).await?;
+        let session_config = client_session_config(&config);
+        Ok(Self {
+            ctx: SessionContext::with_config(session_config.clone()),
+            config: effective_config(&config, &session_config),
+            ballista_config: config,
+            session_id,
+            scheduler,
+            scheduler_url,
+            executors: opt.executors,
+            job_id: Mutex::new(None),
+        })
+    }
+
+    fn engine_version(&self) -> String {
+        BALLISTA_VERSION.to_string()
+    }
+
+    fn datafusion_version(&self) -> String {
+        DATAFUSION_VERSION.to_string()
+    }
+
+    fn build_info(&self) -> BTreeMap<String, String> {
+        build_info()
+    }
+
+    async fn cluster_versions(&self) -> Result<BTreeMap<String, String>> {
+        let mut versions = BTreeMap::new();
+        match &self.scheduler_url {
+            None => {
+                // the in-process cluster is built from the same crates as the runner
+                versions.insert("scheduler".to_string(), BALLISTA_VERSION.to_string());
+                for i in 0..self.executors {
+                    versions.insert(format!("executor.{}", i + 1), BALLISTA_VERSION.to_string());
+                }
+            }
+            Some(url) => {
+                let state: serde_json::Value = reqwest::get(format!("{}/api/state", url))
+                    .await?
+                    .json()
+                    .await?;
+                match state["version"].as_str() {
+                    Some(version) => {
+                        versions.insert("scheduler".to_string(), version.to_string());
+                    }
+                    None => println!("Warning! The scheduler didn't report its version"),
+                }
+                // the executors reported by /api/executors have an id, host, port and last seen
+                // time, but no version
+                println!(
+                    "Warning! The scheduler doesn't report executor versions, so they are not \
+                     recorded"
+                );
+            }
+        }
+        Ok(versions)
+    }
+
+    fn config(&self) -> HashMap<String, String> {
+        self.config.clone()
+    }
+
+    fn error_category(&self, error: &Error) -> Option<ErrorCategory> {
+        error_category(error)
+    }
+
+    async fn register_table(&self, table: &Table) -> Result<()> {
+        if let FileFormat::Json(_) | FileFormat::Arrow = table.format {
+            return Err(format!(
+                "Table {}: {:?} files are not supported by Ballista",
+                table.name, table.format
+            )
+            .into());
+        }
+        // the schema is inferred on the client, the same way BallistaContext::register_parquet does
+        let provider = table_provider(&self.ctx.state(), table).await?;
+        self.ctx.register_table(&table.name, provider)?;
+        Ok(())
+    }
+
+    async fn plan(&self, sql: &str) -> Result<BallistaPlan> {
+        let df = self.ctx.sql(sql).await?;
+        Ok(BallistaPlan {
+            plan: df.logical_plan().clone(),
+            df,
+        })
+    }
+
+    fn optimize(&self, plan: BallistaPlan) -> Result<BallistaPlan> {
+        // the client optimizes the plan before submitting it, as DataFrame::execute_stream does
+        Ok(BallistaPlan {
+            plan: plan.df.clone().into_optimized_plan()?,
+            df: plan.df,
+        })
+    }
+
+    async fn create_physical_plan(&self, plan: &BallistaPlan) -> Result<JobPlan> {
+        Ok(JobPlan {
+            plan: plan.plan.clone(),
+        })
+    }
+
+    fn creates_physical_plan(&self) -> bool {
+        // the scheduler plans the stages when the job is submitted, as part of the execution
+        false
+    }
+
+    async fn execute(&self, job: &JobPlan, mode: ResultMode) -> Result<ResultSet> {
+        let start = Instant::now();
+        let stream = self.execute_job(&job.plan).await?;
+        read_stream(stream, mode, start).await
+    }
+
+    async fn cancel(&self) -> Result<()> {
+        // only the job of this runner's query is cancelled, as the scheduler may be shared
+        let job_id = match self.job_id.lock().unwrap().take() {
+            Some(job_id) => job_id,
+            None => return Ok(()),
+        };
+        println!("Cancelling job {}", job_id);
+        let result = self
+            .scheduler
+            .clone()
+            .cancel_job(CancelJobParams {
+                job_id: job_id.clone(),
+            })
+            .await?
+            .into_inner();
+        if !result.cancelled {
+            return Err(format!("the scheduler did not cancel job {}", job_id).into());
+        }
+        Ok(())
+    }
+
+    fn output_summary(&self, results: &ResultSet) -> OutputSummary {
+        results.summary.clone()
+    }
+
+    fn explain(&self, plan: &BallistaPlan) -> Result<String> {
+        Ok(format!("{}", plan.plan.display_indent()))
+    }
+
+    fn is_ordered(&self, plan: &BallistaPlan) -> Result<bool> {
+        Ok(is_ordered(&plan.plan))
+    }
+
+    fn output_rows(&self, results: &ResultSet) -> Result<Vec<Vec<String>>> {
+        batches_to_rows(&results.batches)
+    }
+
+    #[cfg(feature = "qpml")]
+    fn qpml(&self, plan: &BallistaPlan) -> Result<Option<String>> {
+        qpml(&plan.df)
+    }
+
+    async fn write_output(&self, results: ResultSet, prefix: &str, parquet: bool) -> Result<()> {
+        write_batches(&results.batches, prefix, parquet)
+    }
+}
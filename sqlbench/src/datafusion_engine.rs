@@ -0,0 +1,195 @@
+#[cfg(feature = "qpml")]
+use crate::util::qpml;
+use crate::util::{
+    batches_to_rows, build_info, error_category, is_ordered, read_stream, table_provider,
+    write_batches, ResultSet,
+};
+use async_trait::async_trait;
+use datafusion::config::ConfigOptions;
+use datafusion::physical_plan::display::DisplayableExecutionPlan;
+use datafusion::physical_plan::planner::DefaultPhysicalPlanner;
+use datafusion::physical_plan::{
+    execute_stream, DisplayFormatType, ExecutionPlan, PhysicalPlanner,
+};
+use datafusion::prelude::{DataFrame, SessionConfig, SessionContext};
+use datafusion::scalar::ScalarValue;
+use datafusion::DATAFUSION_VERSION;
+use sqlbench_core::{
+    read_config_file, Engine, Error, ErrorCategory, OperatorMetrics, Opt, OutputSummary,
+    QueryMetrics, Result, ResultMode, Table,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub struct DataFusionEngine {
+    ctx: SessionContext,
+}
+
+/// Results of a query and the physical plan that produced them, with its metrics
+pub struct QueryOutput {
+    results: ResultSet,
+    plan: Arc<dyn ExecutionPlan>,
+}
+
+#[async_trait]
+impl Engine for DataFusionEngine {
+    type Plan = DataFrame;
+    type PhysicalPlan = Arc<dyn ExecutionPlan>;
+    type Output = QueryOutput;
+
+    async fn try_new(opt: &Opt) -> Result<Self> {
+        let mut config = SessionConfig::new().with_target_partitions(opt.concurrency as usize);
+        if let Some(config_path) = &opt.config_path {
+            // SessionConfig::set panics on invalid keys and values
+            let mut options = ConfigOptions::new();
+            for (key, value) in read_config_file(config_path)? {
+                options.set(&key, &value).map_err(|e| {
+                    format!(
+                        "Invalid config {}={} in {}: {}",
+                        key,
+                        value,
+                        config_path.display(),
+                        e
+                    )
+                })?;
+                config = config.set(&key, ScalarValue::Utf8(Some(value)));
+            }
+        }
+        Ok(Self {
+            ctx: SessionContext::with_config(config),
+        })
+    }
+
+    fn engine_version(&self) -> String {
+        DATAFUSION_VERSION.to_string()
+    }
+
+    fn datafusion_version(&self) -> String {
+        DATAFUSION_VERSION.to_string()
+    }
+
+    fn build_info(&self) -> BTreeMap<String, String> {
+        build_info()
+    }
+
+    fn config(&self) -> HashMap<String, String> {
+        let mut config = HashMap::new();
+        for entry in self.ctx.copied_config().config_options().entries() {
+            if let Some(ref value) = entry.value {
+                config.insert(entry.key, value.to_string());
+            }
+        }
+        config
+    }
+
+    fn error_category(&self, error: &Error) -> Option<ErrorCategory> {
+        error_category(error)
+    }
+
+    async fn register_table(&self, table: &Table) -> Result<()> {
+        let provider = table_provider(&self.ctx.state(), table).await?;
+        self.ctx.register_table(table.name.as_str(), provider)?;
+        Ok(())
+    }
+
+    async fn plan(&self, sql: &str) -> Result<DataFrame> {
+        Ok(self.ctx.sql(sql).await?)
+    }
+
+    fn optimize(&self, df: DataFrame) -> Result<DataFrame> {
+        let state = self.ctx.state();
+        let plan = state.optimize(df.logical_plan())?;
+        Ok(DataFrame::new(state, plan))
+    }
+
+    async fn create_physical_plan(&self, df: &DataFrame) -> Result<Arc<dyn ExecutionPlan>> {
+        // DataFrame::create_physical_plan would optimize the logical plan again
+        let planner = DefaultPhysicalPlanner::default();
+        Ok(planner
+            .create_physical_plan(df.logical_plan(), &self.ctx.state())
+            .await?)
+    }
+
+    async fn execute(
+        &self,
+        plan: &Arc<dyn ExecutionPlan>,
+        mode: ResultMode,
+    ) -> Result<QueryOutput> {
+        let start = Instant::now();
+        let stream = execute_stream(plan.clone(), self.ctx.task_ctx())?;
+        Ok(QueryOutput {
+            results: read_stream(stream, mode, start).await?,
+            plan: plan.clone(),
+        })
+    }
+
+    fn output_summary(&self, output: &QueryOutput) -> OutputSummary {
+        output.results.summary.clone()
+    }
+
+    fn explain(&self, df: &DataFrame) -> Result<String> {
+        let plan = df.clone().into_optimized_plan()?;
+        Ok(format!("{}", plan.display_indent()))
+    }
+
+    fn is_ordered(&self, df: &DataFrame) -> Result<bool> {
+        Ok(is_ordered(&df.clone().into_optimized_plan()?))
+    }
+
+    fn physical_plan(&self, output: &QueryOutput) -> Result<Option<String>> {
+        let plan = DisplayableExecutionPlan::with_metrics(output.plan.as_ref());
+        Ok(Some(format!("{}", plan.indent())))
+    }
+
+    fn metrics(&self, output: &QueryOutput) -> Result<Option<QueryMetrics>> {
+        let mut operators = vec![];
+        operator_metrics(output.plan.as_ref(), 0, &mut operators);
+        Ok(Some(QueryMetrics::from_operators(operators)))
+    }
+
+    fn output_rows(&self, output: &QueryOutput) -> Result<Vec<Vec<String>>> {
+        batches_to_rows(&output.results.batches)
+    }
+
+    #[cfg(feature = "qpml")]
+    fn qpml(&self, df: &DataFrame) -> Result<Option<String>> {
+        qpml(df)
+    }
+
+    async fn write_output(&self, output: QueryOutput, prefix: &str, parquet: bool) -> Result<()> {
+        write_batches(&output.results.batches, prefix, parquet)
+    }
+}
+
+/// Collect the metrics of an operator and its children, summed over all partitions
+fn operator_metrics(plan: &dyn ExecutionPlan, depth: usize, operators: &mut Vec<OperatorMetrics>) {
+    let mut metrics = BTreeMap::new();
+    if let Some(metrics_set) = plan.metrics() {
+        for metric in metrics_set.aggregate_by_name().iter() {
+            let value = metric.value();
+            metrics.insert(value.name().to_string(), value.as_usize());
+        }
+    }
+    // operators display as e.g. `ParquetExec: limit=None, partitions=...`
+    let display = format!("{}", OneLine(plan));
+    let operator = display.split(':').next().unwrap_or_default().trim();
+    operators.push(OperatorMetrics {
+        operator: operator.to_string(),
+        depth,
+        metrics,
+    });
+    for child in plan.children() {
+        operator_metrics(child.as_ref(), depth + 1, operators);
+    }
+}
+
+/// Single line description of an operator, without its children
+struct OneLine<'a>(&'a dyn ExecutionPlan);
+
+impl fmt::Display for OneLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_as(DisplayFormatType::Default, f)
+    }
+}
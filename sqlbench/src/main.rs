@@ -0,0 +1,42 @@
+#[cfg(feature = "ballista")]
+mod ballista_engine;
+#[cfg(feature = "datafusion")]
+mod datafusion_engine;
+mod util;
+
+use sqlbench_core::{Command, Opt, Result};
+use structopt::StructOpt;
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    match Command::from_args() {
+        Command::Run(opt) => {
+            if run(&opt).await? > 0 {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Compare(opt) => {
+            if sqlbench_core::compare(&opt)? > 0 {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run(opt: &Opt) -> Result<usize> {
+    match opt.engine.as_str() {
+        #[cfg(feature = "datafusion")]
+        "datafusion" => sqlbench_core::run::<datafusion_engine::DataFusionEngine>(opt).await,
+        #[cfg(feature = "ballista")]
+        "ballista-standalone" | "ballista-remote" => {
+            sqlbench_core::run::<ballista_engine::BallistaEngine>(opt).await
+        }
+        other => Err(format!(
+            "Engine {} is not supported by this build. Check the enabled cargo features.",
+            other
+        )
+        .into()),
+    }
+}
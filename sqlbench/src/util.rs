@@ -0,0 +1,350 @@
+use datafusion::arrow::array::Array;
+use datafusion::arrow::csv;
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::display::array_value_to_string;
+use datafusion::datasource::file_format::arrow::ArrowFormat;
+use datafusion::datasource::file_format::avro::AvroFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::file_type::FileCompressionType;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::listing::{
+    ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
+};
+use datafusion::datasource::view::ViewTable;
+use datafusion::datasource::{provider_as_source, TableProvider};
+use datafusion::error::DataFusionError;
+use datafusion::execution::context::SessionState;
+use datafusion::logical_expr::{Expr, LogicalPlan, LogicalPlanBuilder};
+use datafusion::parquet::arrow::ArrowWriter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::prelude::col;
+#[cfg(feature = "qpml")]
+use datafusion::prelude::DataFrame;
+use futures::StreamExt;
+#[cfg(feature = "qpml")]
+use qpml::from_datafusion;
+use sqlbench_core::{
+    Column, Compression, Error, ErrorCategory, FileFormat, OutputSummary, Result, ResultMode, Table,
+};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Name of the field after the trailing delimiter of dbgen files, which is always empty
+const TRAILING_FIELD: &str = "__trailing_delimiter";
+
+/// Versions and sources of the DataFusion and Ballista crates from Cargo.lock, and the git commit
+/// of the runner, recorded by the build script
+pub fn build_info() -> BTreeMap<String, String> {
+    let mut build = env!("SQLBENCH_DEPENDENCIES")
+        .split(';')
+        .filter_map(|dependency| dependency.split_once('='))
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    build.insert("sqlbench".to_string(), env!("SQLBENCH_GIT_SHA").to_string());
+    build
+}
+
+/// Format record batches as rows of strings, with NULL as an empty string
+pub fn batches_to_rows(batches: &[RecordBatch]) -> Result<Vec<Vec<String>>> {
+    let mut rows = vec![];
+    for batch in batches {
+        for row in 0..batch.num_rows() {
+            let mut values = Vec::with_capacity(batch.num_columns());
+            for column in batch.columns() {
+                if column.is_null(row) {
+                    values.push(String::new());
+                } else {
+                    values.push(array_value_to_string(column, row)?);
+                }
+            }
+            rows.push(values);
+        }
+    }
+    Ok(rows)
+}
+
+/// Write query results to `{prefix}.csv` and optionally `{prefix}.parquet`
+pub fn write_batches(batches: &[RecordBatch], prefix: &str, parquet: bool) -> Result<()> {
+    if batches.is_empty() {
+        println!("Empty result set returned");
+        return Ok(());
+    }
+
+    let mut writer = csv::Writer::new(File::create(format!("{}.csv", prefix))?);
+    for batch in batches {
+        writer.write(batch)?;
+    }
+
+    if parquet {
+        let file = File::create(format!("{}.parquet", prefix))?;
+        let mut writer = ArrowWriter::try_new(file, batches[0].schema(), None)?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.close()?;
+    }
+    Ok(())
+}
+
+/// Optimized logical plan in QPML format
+#[cfg(feature = "qpml")]
+pub fn qpml(df: &DataFrame) -> Result<Option<String>> {
+    let plan = df.clone().into_optimized_plan()?;
+    let qpml = from_datafusion(&plan);
+    Ok(Some(serde_yaml::to_string(&qpml)?))
+}
+
+/// Result batches of a query, when collected, and a summary of the output
+#[derive(Default)]
+pub struct ResultSet {
+    pub batches: Vec<RecordBatch>,
+    pub summary: OutputSummary,
+}
+
+/// Consume a stream of result batches according to the result mode. The time to the first
+/// batch is measured from `start`.
+pub async fn read_stream(
+    mut stream: SendableRecordBatchStream,
+    mode: ResultMode,
+    start: Instant,
+) -> Result<ResultSet> {
+    let mut results = ResultSet::default();
+    if mode != ResultMode::Stream {
+        results.summary.rows = Some(0);
+        results.summary.bytes = Some(0);
+    }
+    while let Some(batch) = stream.next().await {
+        let batch = batch?;
+        if results.summary.time_to_first_batch.is_none() {
+            results.summary.time_to_first_batch = Some(start.elapsed());
+        }
+        if mode == ResultMode::Stream {
+            continue;
+        }
+        let bytes = batch
+            .columns()
+            .iter()
+            .map(|array| array.get_array_memory_size())
+            .sum::<usize>();
+        results.summary.rows = results.summary.rows.map(|n| n + batch.num_rows());
+        results.summary.bytes = results.summary.bytes.map(|n| n + bytes);
+        if mode == ResultMode::Collect {
+            results.batches.push(batch);
+        }
+    }
+    Ok(results)
+}
+
+/// Category of a DataFusion error
+pub fn error_category(error: &Error) -> Option<ErrorCategory> {
+    error
+        .downcast_ref::<DataFusionError>()
+        .map(datafusion_error_category)
+}
+
+fn datafusion_error_category(error: &DataFusionError) -> ErrorCategory {
+    match error {
+        DataFusionError::SQL(_) => ErrorCategory::SqlParse,
+        DataFusionError::Plan(_)
+        | DataFusionError::SchemaError(_)
+        | DataFusionError::NotImplemented(_) => ErrorCategory::Plan,
+        DataFusionError::ResourcesExhausted(_) => ErrorCategory::ResourcesExhausted,
+        DataFusionError::IoError(_) | DataFusionError::ObjectStore(_) => ErrorCategory::Io,
+        DataFusionError::Context(_, e) => datafusion_error_category(e),
+        _ => ErrorCategory::Execution,
+    }
+}
+
+/// Whether the rows produced by a logical plan are ordered by an ORDER BY
+pub fn is_ordered(plan: &LogicalPlan) -> bool {
+    match plan {
+        LogicalPlan::Sort(_) => true,
+        LogicalPlan::Projection(_) | LogicalPlan::Limit(_) | LogicalPlan::SubqueryAlias(_) => {
+            plan.inputs().iter().any(|input| is_ordered(input))
+        }
+        _ => false,
+    }
+}
+
+/// DataFusion compression type for text files
+fn compression_type(compression: Compression) -> FileCompressionType {
+    match compression {
+        Compression::Uncompressed => FileCompressionType::UNCOMPRESSED,
+        Compression::Gzip => FileCompressionType::GZIP,
+        Compression::Bzip2 => FileCompressionType::BZIP2,
+        Compression::Xz => FileCompressionType::XZ,
+        Compression::Zstd => FileCompressionType::ZSTD,
+    }
+}
+
+/// Parse an Arrow data type name such as `Int64`, `Utf8` or `Decimal128(15, 2)`
+fn parse_data_type(name: &str) -> Result<DataType> {
+    let data_type = match name.trim() {
+        "Boolean" => DataType::Boolean,
+        "Int8" => DataType::Int8,
+        "Int16" => DataType::Int16,
+        "Int32" => DataType::Int32,
+        "Int64" => DataType::Int64,
+        "UInt8" => DataType::UInt8,
+        "UInt16" => DataType::UInt16,
+        "UInt32" => DataType::UInt32,
+        "UInt64" => DataType::UInt64,
+        "Float32" => DataType::Float32,
+        "Float64" => DataType::Float64,
+        "Utf8" => DataType::Utf8,
+        "LargeUtf8" => DataType::LargeUtf8,
+        "Date32" => DataType::Date32,
+        "Date64" => DataType::Date64,
+        other => match other
+            .strip_prefix("Decimal128(")
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+        {
+            Some((precision, scale)) => {
+                DataType::Decimal128(precision.trim().parse()?, scale.trim().parse()?)
+            }
+            None => return Err(format!("Unsupported data type: {}", name).into()),
+        },
+    };
+    Ok(data_type)
+}
+
+/// Partition columns of a table with their Arrow data types
+fn partition_cols(table: &Table) -> Result<Vec<(String, DataType)>> {
+    table
+        .partition_cols
+        .iter()
+        .map(|(name, data_type)| Ok((name.clone(), parse_data_type(data_type)?)))
+        .collect()
+}
+
+/// Arrow schema of a declared table schema, with a field for the empty value after a trailing
+/// delimiter
+fn schema(columns: &[Column], trailing_delimiter: bool) -> Result<Schema> {
+    let mut fields = columns
+        .iter()
+        .map(|column| {
+            Ok(Field::new(
+                &column.name,
+                parse_data_type(&column.data_type)?,
+                column.nullable,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if trailing_delimiter {
+        fields.push(Field::new(TRAILING_FIELD, DataType::Utf8, true));
+    }
+    Ok(Schema::new(fields))
+}
+
+/// Table provider for a table definition. The schema is inferred from the files unless declared.
+/// The empty field after the trailing delimiter of dbgen files is projected away.
+pub async fn table_provider(state: &SessionState, table: &Table) -> Result<Arc<dyn TableProvider>> {
+    let trailing_delimiter =
+        matches!(&table.format, FileFormat::Csv(options) if options.trailing_delimiter);
+    let file_format: Arc<dyn datafusion::datasource::file_format::FileFormat> = match &table.format
+    {
+        FileFormat::Parquet => Arc::new(ParquetFormat::default()),
+        FileFormat::Csv(options) => Arc::new(
+            CsvFormat::default()
+                .with_has_header(options.has_header)
+                .with_delimiter(options.delimiter)
+                .with_file_compression_type(compression_type(options.compression)),
+        ),
+        FileFormat::Json(compression) => Arc::new(
+            JsonFormat::default().with_file_compression_type(compression_type(*compression)),
+        ),
+        FileFormat::Avro => Arc::new(AvroFormat::default()),
+        FileFormat::Arrow => Arc::new(ArrowFormat::default()),
+    };
+
+    let mut options = ListingOptions::new(file_format)
+        .with_file_extension(table.format.file_suffix())
+        .with_table_partition_cols(partition_cols(table)?)
+        .with_target_partitions(state.config().target_partitions());
+    if !table.sort_order.is_empty() {
+        let sort_order = table
+            .sort_order
+            .iter()
+            .map(|column| col(column.name.as_str()).sort(!column.descending, column.nulls_first))
+            .collect();
+        options = options.with_file_sort_order(Some(sort_order));
+    }
+
+    let paths = table
+        .paths
+        .iter()
+        .map(ListingTableUrl::parse)
+        .collect::<datafusion::error::Result<Vec<_>>>()?;
+    let config = ListingTableConfig::new_with_multi_paths(paths).with_listing_options(options);
+    let config = match &table.schema {
+        Some(columns) => config.with_schema(Arc::new(schema(columns, trailing_delimiter)?)),
+        None => config.infer_schema(state).await?,
+    };
+    let listing_table = Arc::new(ListingTable::try_new(config)?);
+    if !trailing_delimiter {
+        return Ok(listing_table);
+    }
+
+    // the trailing field is the last field of the file schema, before any partition columns
+    let schema = listing_table.schema();
+    let trailing = schema.fields().len() - table.partition_cols.len() - 1;
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != trailing)
+        .map(|(_, field)| Expr::Column(datafusion::common::Column::from_name(field.name())))
+        .collect::<Vec<_>>();
+    let plan =
+        LogicalPlanBuilder::scan(table.name.clone(), provider_as_source(listing_table), None)?
+            .project(columns)?
+            .build()?;
+    Ok(Arc::new(ViewTable::try_new(plan, None)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlbench_core::Manifest;
+    use std::path::Path;
+
+    #[test]
+    fn data_types() -> Result<()> {
+        assert_eq!(parse_data_type("Int64")?, DataType::Int64);
+        assert_eq!(parse_data_type(" Date32 ")?, DataType::Date32);
+        assert_eq!(
+            parse_data_type("Decimal128(15, 2)")?,
+            DataType::Decimal128(15, 2)
+        );
+        assert_eq!(
+            parse_data_type("Decimal128(38,10)")?,
+            DataType::Decimal128(38, 10)
+        );
+        for name in [
+            "Int128",
+            "Decimal128(15)",
+            "Decimal128(x, 2)",
+            "decimal(15, 2)",
+        ] {
+            assert!(parse_data_type(name).is_err(), "{}", name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tpch_manifest_types() -> Result<()> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tpch-tables.yaml");
+        for table in Manifest::read(&path)?.tables(Path::new("/data"))? {
+            let columns = table.schema.as_ref().unwrap();
+            let schema = schema(columns, true)?;
+            // every declared column plus the empty field after the trailing delimiter
+            assert_eq!(schema.fields().len(), columns.len() + 1, "{}", table.name);
+        }
+        Ok(())
+    }
+}